@@ -1,15 +1,98 @@
-use crate::game::{Config, Game, Layer, Tile, VisibleCellData, VisibleEntityData};
+use crate::{
+    export::{self, ExportMode},
+    game::{
+        is_wall_known_at, omniscient_visibility_grid, Config, Game, GameOutcome, Layer,
+        ShotOutcome, Tile, VisibleCellData, VisibleEntityData,
+    },
+    map_file::{MapCell, MapFile},
+    rng::RngStreams,
+    seed::Seed,
+    terrain::Terrain,
+};
 use gridbugs::{
-    chargrid::{control_flow::*, prelude::*},
+    chargrid::{
+        border::{BorderPadding, BorderStyle},
+        control_flow::*,
+        input::{keys, MouseButton},
+        menu::{
+            builder::{identifier, item, menu_builder},
+            MenuItemIdentifierBoxed,
+        },
+        prelude::*,
+        text::StyledString,
+    },
     coord_2d::Size,
     direction::CardinalDirection,
     rgb_int::{Rgb24, Rgba32},
-    visible_area_detection::CellVisibility,
+    visible_area_detection::{CellVisibility, VisibilityGrid},
 };
+use log::{info, warn};
+use std::{path::PathBuf, time::Duration};
 
 // An update to the game state
 enum GameAction {
     Move(CardinalDirection),
+    // Take a step towards the nearest part of the level which hasn't been seen
+    AutoExplore,
+    ExportMap(ExportMode),
+}
+
+// Input while choosing where to fire a projectile
+enum TargetingAction {
+    MoveCursor(CardinalDirection),
+    // Move the cursor to the next creature in view, in order of distance from the player
+    NextTarget,
+    Fire,
+}
+
+fn targeting_action_from_input(input: Input) -> Option<TargetingAction> {
+    use CardinalDirection::*;
+    use TargetingAction::*;
+    match input.keyboard()? {
+        KeyboardInput::Left => Some(MoveCursor(West)),
+        KeyboardInput::Right => Some(MoveCursor(East)),
+        KeyboardInput::Up => Some(MoveCursor(North)),
+        KeyboardInput::Down => Some(MoveCursor(South)),
+        keys::TAB => Some(NextTarget),
+        KeyboardInput::Char('f') | keys::RETURN => Some(Fire),
+        _ => None,
+    }
+}
+
+// A command only available when debug commands are enabled
+enum DebugAction {
+    ToggleOmniscient,
+    TeleportToCursor,
+    // Move the cursor with the keyboard, for when there is no mouse (e.g. in a terminal)
+    MoveCursor(CardinalDirection),
+    ToggleEntityList,
+    // Scroll the list of entities by a number of lines
+    ScrollEntityList(i32),
+    ToggleInspector,
+}
+
+// The number of lines the entity list scrolls by at a time
+const ENTITY_LIST_SCROLL_LINES: i32 = 10;
+
+fn debug_action_from_input(input: Input) -> Option<DebugAction> {
+    use CardinalDirection::*;
+    match input {
+        Input::Keyboard(KeyboardInput::Char('O')) => Some(DebugAction::ToggleOmniscient),
+        Input::Keyboard(KeyboardInput::Char('T')) => Some(DebugAction::TeleportToCursor),
+        Input::Keyboard(KeyboardInput::Char('H')) => Some(DebugAction::MoveCursor(West)),
+        Input::Keyboard(KeyboardInput::Char('J')) => Some(DebugAction::MoveCursor(South)),
+        Input::Keyboard(KeyboardInput::Char('K')) => Some(DebugAction::MoveCursor(North)),
+        Input::Keyboard(KeyboardInput::Char('L')) => Some(DebugAction::MoveCursor(East)),
+        Input::Keyboard(KeyboardInput::Char('E')) => Some(DebugAction::ToggleEntityList),
+        Input::Keyboard(KeyboardInput::PageUp) => {
+            Some(DebugAction::ScrollEntityList(-ENTITY_LIST_SCROLL_LINES))
+        }
+        Input::Keyboard(KeyboardInput::PageDown) => {
+            Some(DebugAction::ScrollEntityList(ENTITY_LIST_SCROLL_LINES))
+        }
+        Input::Keyboard(KeyboardInput::Char('I')) => Some(DebugAction::ToggleInspector),
+        _ => None,
+    }
 }
 
 // Associate game actions with input events
@@ -23,6 +106,9 @@ fn game_action_from_input(input: Input) -> Option<GameAction> {
                 KeyboardInput::Right => Some(Move(East)),
                 KeyboardInput::Up => Some(Move(North)),
                 KeyboardInput::Down => Some(Move(South)),
+                KeyboardInput::Char('z') => Some(AutoExplore),
+                KeyboardInput::Char('x') => Some(ExportMap(ExportMode::Remembered)),
+                KeyboardInput::Char('X') => Some(ExportMap(ExportMode::WholeLevel)),
                 _ => None,
             }
         }
@@ -45,24 +131,34 @@ impl Tint for LightBlend {
     }
 }
 
-// The state of the game
-struct GameData {
-    game: Game,
+// Renders the contents of a visibility grid, dimming cells which are remembered but not currently
+// visible. This is used both to draw the game and to export images of the map.
+pub struct VisibilityGridRenderer<'a> {
+    pub visibility_grid: &'a VisibilityGrid<VisibleCellData>,
+    // The world coordinate drawn in the top-left corner of the screen
+    pub offset: Coord,
 }
 
-impl GameData {
-    fn new(screen_size: Size, config: Config) -> Self {
-        let game = Game::new(screen_size, config);
-        Self { game }
-    }
-
-    // Update the game state by applying a game action
-    fn handle_game_action(&mut self, game_action: GameAction) {
-        match game_action {
-            GameAction::Move(direction) => self.game.move_player(direction),
+// Returns the world coordinate to draw in the top-left corner of a view, such that the view is
+// centred on `focus` without scrolling past the edges of the world. Worlds smaller than the view
+// are centred within it.
+fn camera_offset(focus: Coord, world_size: Size, view_size: Size) -> Coord {
+    let axis_offset = |focus: i32, world_length: u32, view_length: u32| {
+        let world_length = world_length as i32;
+        let view_length = view_length as i32;
+        if world_length <= view_length {
+            (world_length - view_length) / 2
+        } else {
+            (focus - view_length / 2).clamp(0, world_length - view_length)
         }
-    }
+    };
+    Coord::new(
+        axis_offset(focus.x, world_size.width(), view_size.width()),
+        axis_offset(focus.y, world_size.height(), view_size.height()),
+    )
+}
 
+impl<'a> VisibilityGridRenderer<'a> {
     // Associate each tile with a description of how to render it
     fn render_cell_from_entity_data(
         &self,
@@ -71,8 +167,17 @@ impl GameData {
     ) -> RenderCell {
         match visible_entity_data.tile {
             Tile::Player => RenderCell::BLANK.with_character('@').with_bold(true),
+            Tile::Goblin => RenderCell::BLANK
+                .with_character('g')
+                .with_bold(true)
+                .with_foreground(Rgba32::new_rgb(223, 63, 31)),
+            Tile::Projectile => RenderCell::BLANK
+                .with_character('*')
+                .with_bold(true)
+                .with_foreground(Rgba32::new_rgb(255, 223, 63)),
             Tile::Wall => {
-                let is_wall_below = self.game.is_wall_known_at(coord + Coord::new(0, 1));
+                let is_wall_below =
+                    is_wall_known_at(self.visibility_grid, coord + Coord::new(0, 1));
                 if is_wall_below {
                     RenderCell::BLANK
                         .with_character(' ')
@@ -85,7 +190,8 @@ impl GameData {
                 }
             }
             Tile::CaveWall => {
-                let is_wall_below = self.game.is_wall_known_at(coord + Coord::new(0, 1));
+                let is_wall_below =
+                    is_wall_known_at(self.visibility_grid, coord + Coord::new(0, 1));
                 if is_wall_below {
                     RenderCell::BLANK
                         .with_character(' ')
@@ -126,11 +232,18 @@ impl GameData {
                     .with_background(colour_hint.background.to_rgba32(255))
                     .with_foreground(colour_hint.foreground.to_rgba32(255))
             }
+            Tile::Glyph {
+                character, colour, ..
+            } => RenderCell::BLANK
+                .with_character(character)
+                .with_bold(true)
+                .with_foreground(colour.to_rgba32(255)),
         }
     }
 
     fn layer_depth(layer: Layer) -> i8 {
         match layer {
+            Layer::Projectile => 3,
             Layer::Character => 2,
             Layer::Feature => 1,
             Layer::Floor => 0,
@@ -145,9 +258,10 @@ impl GameData {
         ctx: Ctx,
         fb: &mut FrameBuffer,
     ) {
+        // The wall-below check uses world coordinates, so translate to screen coordinates last
         let render_cell = self.render_cell_from_entity_data(visible_entity_data, coord);
         let depth = Self::layer_depth(layer);
-        fb.set_cell_relative_to_ctx(ctx, coord, depth, render_cell);
+        fb.set_cell_relative_to_ctx(ctx, coord - self.offset, depth, render_cell);
     }
 
     fn render_cell(&self, coord: Coord, cell: &VisibleCellData, ctx: Ctx, fb: &mut FrameBuffer) {
@@ -157,9 +271,11 @@ impl GameData {
             });
     }
 
-    fn render(&self, ctx: Ctx, fb: &mut FrameBuffer) {
-        for (coord, visibility) in self.game.enumerate_cell_visibility() {
-            match visibility {
+    pub fn render(&self, ctx: Ctx, fb: &mut FrameBuffer) {
+        // Only visit the cells of the world which are on screen
+        for screen_coord in ctx.bounding_box.size().coord_iter_row_major() {
+            let coord = screen_coord + self.offset;
+            match self.visibility_grid.get_visibility(coord) {
                 CellVisibility::Never => (),
                 CellVisibility::Previous(data)
                 | CellVisibility::Current {
@@ -184,30 +300,787 @@ impl GameData {
     }
 }
 
+// What the overview shows for a single cell of the world, in increasing order of importance. When
+// several cells share part of a glyph, the most important one is shown.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum OverviewCell {
+    Unknown,
+    Wall,
+    Water,
+    Floor,
+    // A cell the player has seen which is next to a cell they haven't seen
+    Frontier,
+    Player,
+}
+
+impl OverviewCell {
+    fn colour(self) -> Rgba32 {
+        match self {
+            Self::Unknown => Rgba32::new_grey(0),
+            Self::Wall => Rgba32::new_grey(63),
+            Self::Water => Rgba32::new_rgb(0, 63, 191),
+            Self::Floor => Rgba32::new_grey(159),
+            Self::Frontier => Rgba32::new_rgb(255, 127, 0),
+            Self::Player => Rgba32::new_rgb(255, 255, 0),
+        }
+    }
+}
+
+// Renders the player's memory of the entire level at reduced scale. Each glyph is a half block
+// whose top and bottom halves each show two horizontally-adjacent cells, so each glyph covers a
+// 2x2 square of cells.
+struct OverviewRenderer<'a> {
+    visibility_grid: &'a VisibilityGrid<VisibleCellData>,
+    world_size: Size,
+    player_coord: Coord,
+}
+
+impl<'a> OverviewRenderer<'a> {
+    const CELLS_PER_GLYPH: Size = Size::new_u16(2, 2);
+
+    fn overview_cell(&self, coord: Coord) -> OverviewCell {
+        if coord == self.player_coord {
+            return OverviewCell::Player;
+        }
+        let data = match self.visibility_grid.get_data(coord) {
+            Some(data) => data,
+            None => return OverviewCell::Unknown,
+        };
+        if is_wall_known_at(self.visibility_grid, coord) {
+            return OverviewCell::Wall;
+        }
+        let is_frontier = CardinalDirection::all().any(|direction| {
+            let neighbour_coord = coord + direction.coord();
+            neighbour_coord.is_valid(self.world_size)
+                && self.visibility_grid.get_data(neighbour_coord).is_none()
+        });
+        if is_frontier {
+            OverviewCell::Frontier
+        } else if let Some(VisibleEntityData {
+            tile: Tile::Water, ..
+        }) = data.entity_data.floor
+        {
+            OverviewCell::Water
+        } else {
+            OverviewCell::Floor
+        }
+    }
+
+    // The most important cell in a horizontal pair of cells starting at the given coord
+    fn overview_cell_pair(&self, coord: Coord) -> OverviewCell {
+        self.overview_cell(coord)
+            .max(self.overview_cell(coord + Coord::new(1, 0)))
+    }
+
+    pub fn render(&self, ctx: Ctx, fb: &mut FrameBuffer) {
+        let world_size = self.world_size;
+        let overview_size = Size::new(
+            world_size.width().div_ceil(Self::CELLS_PER_GLYPH.width()),
+            world_size.height().div_ceil(Self::CELLS_PER_GLYPH.height()),
+        );
+        // Scroll the overview to keep the player in view if it doesn't fit on the screen
+        let player_glyph_coord = Coord::new(
+            self.player_coord.x / Self::CELLS_PER_GLYPH.width() as i32,
+            self.player_coord.y / Self::CELLS_PER_GLYPH.height() as i32,
+        );
+        let offset = camera_offset(player_glyph_coord, overview_size, ctx.bounding_box.size());
+        for screen_coord in ctx.bounding_box.size().coord_iter_row_major() {
+            let glyph_coord = screen_coord + offset;
+            if !glyph_coord.is_valid(overview_size) {
+                continue;
+            }
+            let top_left = Coord::new(
+                glyph_coord.x * Self::CELLS_PER_GLYPH.width() as i32,
+                glyph_coord.y * Self::CELLS_PER_GLYPH.height() as i32,
+            );
+            let top = self.overview_cell_pair(top_left);
+            let bottom = self.overview_cell_pair(top_left + Coord::new(0, 1));
+            let render_cell = RenderCell::BLANK
+                .with_character('▀')
+                .with_foreground(top.colour())
+                .with_background(bottom.colour());
+            fb.set_cell_relative_to_ctx(ctx, screen_coord, 0, render_cell);
+        }
+    }
+}
+
+// Width of the side panel, including a column of padding separating it from the map
+const SIDE_PANEL_WIDTH: u32 = 20;
+// Height of the message log
+const MESSAGE_LOG_HEIGHT: u32 = 5;
+// The side panel and message log are hidden when the map would be smaller than this
+const MIN_MAP_SIZE: Size = Size::new_u16(40, 25);
+
+// How the screen is divided between the map, side panel and message log. Parts of the UI other
+// than the map are hidden on small screens.
+struct Layout {
+    map_size: Size,
+    show_side_panel: bool,
+    show_message_log: bool,
+}
+
+impl Layout {
+    fn new(screen_size: Size) -> Self {
+        let show_side_panel = screen_size.width() >= MIN_MAP_SIZE.width() + SIDE_PANEL_WIDTH;
+        let show_message_log = screen_size.height() >= MIN_MAP_SIZE.height() + MESSAGE_LOG_HEIGHT;
+        let map_size = Size::new(
+            screen_size.width() - if show_side_panel { SIDE_PANEL_WIDTH } else { 0 },
+            screen_size.height()
+                - if show_message_log {
+                    MESSAGE_LOG_HEIGHT
+                } else {
+                    0
+                },
+        );
+        Self {
+            map_size,
+            show_side_panel,
+            show_message_log,
+        }
+    }
+
+    fn map_ctx<'a>(&self, ctx: Ctx<'a>) -> Ctx<'a> {
+        ctx.set_size(self.map_size)
+    }
+
+    // The side panel runs down the right of the screen beside the map and message log
+    fn side_panel_ctx<'a>(&self, ctx: Ctx<'a>) -> Option<Ctx<'a>> {
+        if self.show_side_panel {
+            Some(ctx.add_x(self.map_size.width() as i32 + 1))
+        } else {
+            None
+        }
+    }
+
+    // The message log sits below the map
+    fn message_log_ctx<'a>(&self, ctx: Ctx<'a>) -> Option<Ctx<'a>> {
+        if self.show_message_log {
+            Some(
+                ctx.add_y(self.map_size.height() as i32)
+                    .set_size(Size::new(self.map_size.width(), MESSAGE_LOG_HEIGHT)),
+            )
+        } else {
+            None
+        }
+    }
+}
+
+// Draw a line of text in a given colour
+fn render_text_line(text: &str, colour: Rgba32, coord: Coord, ctx: Ctx, fb: &mut FrameBuffer) {
+    StyledString {
+        string: text.to_string(),
+        style: Style::plain_text().with_foreground(colour),
+    }
+    .render(&(), ctx.add_offset(coord), fb);
+}
+
+// The number of messages remembered by the message log
+const MAX_MESSAGES: usize = 100;
+
+// The time between each step the player takes while travelling to a cell they clicked on
+const TRAVEL_STEP_INTERVAL: Duration = Duration::from_millis(50);
+
+// A journey to a cell the player clicked on, taken one step at a time
+struct Travel {
+    destination: Coord,
+    until_next_step: Duration,
+}
+
+// The state of the game
+struct GameData {
+    game: Game,
+    // Messages to show the player, oldest first
+    message_log: Vec<String>,
+    // Whether to show the overview of the level in place of the map
+    show_overview: bool,
+    // Whether debug commands are enabled
+    debug: bool,
+    // The world coordinate chosen with the mouse pointer or debug cursor keys, used by debug
+    // commands
+    cursor: Option<Coord>,
+    // Whether to list the entities under the cursor and their components (debug only)
+    show_inspector: bool,
+    // Whether to list every entity in the level and their components (debug only)
+    show_entity_list: bool,
+    // The first line of the entity list which is shown
+    entity_list_scroll: usize,
+    // While the player is choosing where to fire, the cell they are aiming at
+    targeting: Option<Coord>,
+    travel: Option<Travel>,
+}
+
+impl GameData {
+    fn new(world_size: Size, config: Config, debug: bool) -> Self {
+        let game = Game::new(world_size, config);
+        let mut message_log = vec![format!("Started a new game (seed {})", game.seed())];
+        if debug {
+            message_log.push(
+                "Debug: O:omniscience T:teleport HJKL:cursor G:choose seed E:entities I:inspector"
+                    .to_string(),
+            );
+        }
+        Self {
+            game,
+            message_log,
+            show_overview: false,
+            debug,
+            cursor: None,
+            show_inspector: false,
+            show_entity_list: false,
+            entity_list_scroll: 0,
+            targeting: None,
+            travel: None,
+        }
+    }
+
+    // Add a message to the log, forgetting the oldest message if the log is full
+    fn push_message(&mut self, message: String) {
+        self.message_log.push(message);
+        if self.message_log.len() > MAX_MESSAGES {
+            self.message_log.remove(0);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.game.reset();
+        self.push_message(format!("Generated a new level (seed {})", self.game.seed()));
+    }
+
+    fn regenerate_with_seed(&mut self, seed: Seed) {
+        // Levels which have been played before are restored as they were left
+        let message = if self.game.seed_history().contains(&seed) {
+            format!("Returned to the level from seed {}", seed)
+        } else {
+            format!("Generated a level from seed {}", seed)
+        };
+        self.push_message(message);
+        self.game.regenerate_with_seed(seed);
+    }
+
+    // Update the game state by applying a game action
+    fn handle_game_action(&mut self, game_action: GameAction) {
+        match game_action {
+            GameAction::Move(direction) => self.game.move_player(direction),
+            GameAction::AutoExplore => {
+                if let Err(e) = self.game.auto_explore() {
+                    self.push_message(format!("Can't explore: {}", e));
+                }
+            }
+            GameAction::ExportMap(mode) => {
+                let path = export::default_path(&self.game, mode);
+                let message = match export::export_png(&self.game, mode, &path) {
+                    Ok(()) => {
+                        info!("Exported map to {}", path.display());
+                        format!("Exported map to {}", path.display())
+                    }
+                    Err(e) => {
+                        warn!("Failed to export map to {}: {}", path.display(), e);
+                        format!("Failed to export map to {}: {}", path.display(), e)
+                    }
+                };
+                self.push_message(message);
+            }
+        }
+    }
+
+    // Start choosing where to fire, aiming at the nearest creature in view if there is one
+    fn start_targeting(&mut self) {
+        let target = self
+            .game
+            .visible_creature_coords()
+            .first()
+            .cloned()
+            .unwrap_or_else(|| self.game.get_player_coord());
+        self.targeting = Some(target);
+        self.push_message(
+            "Aiming: arrows:move tab:next target f/enter:fire escape:cancel".to_string(),
+        );
+    }
+
+    fn handle_targeting_action(&mut self, target: Coord, targeting_action: TargetingAction) {
+        match targeting_action {
+            TargetingAction::MoveCursor(direction) => {
+                let target = target + direction.coord();
+                if target.is_valid(self.game.world_size()) {
+                    self.targeting = Some(target);
+                }
+            }
+            TargetingAction::NextTarget => {
+                let targets = self.game.visible_creature_coords();
+                // Start again from the nearest creature if the cursor isn't on one
+                let index = targets
+                    .iter()
+                    .position(|&coord| coord == target)
+                    .map(|index| (index + 1) % targets.len())
+                    .unwrap_or(0);
+                match targets.get(index) {
+                    Some(&target) => self.targeting = Some(target),
+                    None => self.push_message("There is nothing in view to aim at".to_string()),
+                }
+            }
+            TargetingAction::Fire => {
+                self.targeting = None;
+                if let Err(e) = self.game.fire_projectile(target) {
+                    self.push_message(format!("Can't fire: {}", e));
+                }
+            }
+        }
+    }
+
+    fn handle_debug_action(&mut self, debug_action: DebugAction) {
+        let message = match debug_action {
+            DebugAction::ToggleOmniscient => {
+                let omniscient = !self.game.is_omniscient();
+                self.game.set_omniscient(omniscient);
+                format!("Omniscience {}", if omniscient { "on" } else { "off" })
+            }
+            DebugAction::TeleportToCursor => match self.cursor {
+                None => "Point at the map or use HJKL to choose where to teleport".to_string(),
+                Some(cursor) => match self.game.teleport_player(cursor) {
+                    Ok(()) => format!("Teleported to {:?}", cursor),
+                    Err(e) => format!("Failed to teleport: {}", e),
+                },
+            },
+            DebugAction::MoveCursor(direction) => {
+                // The cursor starts on the player if there isn't one yet
+                let cursor = self.cursor.unwrap_or_else(|| self.game.get_player_coord());
+                let cursor = cursor + direction.coord();
+                if cursor.is_valid(self.game.world_size()) {
+                    self.cursor = Some(cursor);
+                }
+                return;
+            }
+            DebugAction::ToggleEntityList => {
+                self.show_entity_list = !self.show_entity_list;
+                if !self.show_entity_list {
+                    return;
+                }
+                self.entity_list_scroll = 0;
+                let count = self.game.log_all_entities();
+                format!(
+                    "Listing {} entities (also written to the log), PgUp/PgDn to scroll",
+                    count
+                )
+            }
+            DebugAction::ScrollEntityList(lines) => {
+                self.entity_list_scroll = self
+                    .entity_list_scroll
+                    .saturating_add_signed(lines as isize);
+                return;
+            }
+            DebugAction::ToggleInspector => {
+                self.show_inspector = !self.show_inspector;
+                format!(
+                    "Inspector {}",
+                    if self.show_inspector { "on" } else { "off" }
+                )
+            }
+        };
+        self.push_message(message);
+    }
+
+    // The world coordinate drawn in the top-left corner of the map. The camera follows the player.
+    fn camera_offset(&self, map_size: Size) -> Coord {
+        camera_offset(
+            self.game.get_player_coord(),
+            self.game.world_size(),
+            map_size,
+        )
+    }
+
+    // The world coordinate under the mouse pointer, if it's over the map
+    fn world_coord_at_pointer(&self, pointer_coord: Coord, map_ctx: Ctx) -> Option<Coord> {
+        if self.show_overview {
+            return None;
+        }
+        let offset = self.camera_offset(map_ctx.bounding_box.size());
+        map_ctx
+            .bounding_box
+            .coord_absolute_to_relative(pointer_coord)
+            .map(|screen_coord| screen_coord + offset)
+            .filter(|coord| coord.is_valid(self.game.world_size()))
+    }
+
+    // Update the cursor from the position of the mouse pointer
+    fn update_cursor(&mut self, pointer_coord: Coord, map_ctx: Ctx) {
+        self.cursor = self.world_coord_at_pointer(pointer_coord, map_ctx);
+    }
+
+    // Start travelling to the cell under the mouse pointer, taking the first step straight away
+    fn start_travel(&mut self, pointer_coord: Coord, map_ctx: Ctx) {
+        let Some(destination) = self.world_coord_at_pointer(pointer_coord, map_ctx) else {
+            return;
+        };
+        match self.game.travel_towards(destination) {
+            Ok(()) => {
+                self.travel = Some(Travel {
+                    destination,
+                    until_next_step: TRAVEL_STEP_INTERVAL,
+                })
+            }
+            Err(e) => self.push_message(format!("Can't travel there: {}", e)),
+        }
+    }
+
+    // Take the next step towards the travel destination once enough time has passed
+    fn travel_tick(&mut self, since_last_tick: Duration) {
+        let Some(travel) = self.travel.as_mut() else {
+            return;
+        };
+        if self.game.get_player_coord() == travel.destination {
+            self.travel = None;
+            return;
+        }
+        if let Some(until_next_step) = travel.until_next_step.checked_sub(since_last_tick) {
+            travel.until_next_step = until_next_step;
+            return;
+        }
+        travel.until_next_step = TRAVEL_STEP_INTERVAL;
+        let destination = travel.destination;
+        if let Err(e) = self.game.travel_towards(destination) {
+            self.travel = None;
+            self.push_message(format!("Stopped travelling: {}", e));
+        }
+    }
+
+    fn render_map(&self, ctx: Ctx, fb: &mut FrameBuffer) {
+        if self.show_overview {
+            OverviewRenderer {
+                visibility_grid: self.game.visibility_grid(),
+                world_size: self.game.world_size(),
+                player_coord: self.game.get_player_coord(),
+            }
+            .render(ctx, fb);
+            return;
+        }
+        let offset = self.camera_offset(ctx.bounding_box.size());
+        VisibilityGridRenderer {
+            visibility_grid: self.game.visibility_grid(),
+            offset,
+        }
+        .render(ctx, fb);
+        if let Some(target) = self.targeting {
+            for coord in self.game.line_of_fire(target) {
+                fb.set_cell_relative_to_ctx(
+                    ctx,
+                    coord - offset,
+                    10,
+                    RenderCell::BLANK.with_background(Rgba32::new(255, 63, 0, 95)),
+                );
+            }
+            fb.set_cell_relative_to_ctx(
+                ctx,
+                target - offset,
+                11,
+                RenderCell::BLANK.with_background(Rgba32::new(255, 63, 0, 191)),
+            );
+        }
+        if let (true, Some(cursor)) = (self.debug, self.cursor) {
+            fb.set_cell_relative_to_ctx(
+                ctx,
+                cursor - offset,
+                10,
+                RenderCell::BLANK.with_background(Rgba32::new(255, 255, 0, 127)),
+            );
+            if self.show_inspector {
+                self.render_inspector(cursor, cursor - offset, ctx, fb);
+            }
+        }
+        if self.debug && self.show_entity_list {
+            self.render_entity_list(ctx, fb);
+        }
+    }
+
+    // List the entities at the cursor and all their components in a box on the opposite side of
+    // the map to the cursor, so the box doesn't hide the cell being inspected
+    fn render_inspector(&self, cursor: Coord, screen_coord: Coord, ctx: Ctx, fb: &mut FrameBuffer) {
+        let mut lines = vec![format!("Inspecting ({}, {})", cursor.x, cursor.y)];
+        for description in self.game.describe_entities_at(cursor) {
+            lines.push(format!("{:?} {:?}", description.layer, description.entity));
+            lines.extend(
+                description
+                    .components
+                    .iter()
+                    .map(|component| format!("  {}", component)),
+            );
+        }
+        let on_right = screen_coord.x < ctx.bounding_box.size().width() as i32 / 2;
+        render_debug_box(&lines, on_right, ctx, fb);
+    }
+
+    // List every entity in the level and all their components in a box on the left of the map,
+    // starting from the line it has been scrolled to
+    fn render_entity_list(&self, ctx: Ctx, fb: &mut FrameBuffer) {
+        let mut lines = Vec::new();
+        for coord in self.game.world_size().coord_iter_row_major() {
+            for description in self.game.describe_entities_at(coord) {
+                lines.push(format!(
+                    "({}, {}) {:?} {:?}",
+                    coord.x, coord.y, description.layer, description.entity
+                ));
+                lines.extend(
+                    description
+                        .components
+                        .iter()
+                        .map(|component| format!("  {}", component)),
+                );
+            }
+        }
+        let first = self.entity_list_scroll.min(lines.len().saturating_sub(1));
+        render_debug_box(&lines[first..], false, ctx, fb);
+    }
+    // Show the most recent messages which fit in the log, with the newest at the bottom
+    fn render_message_log(&self, ctx: Ctx, fb: &mut FrameBuffer) {
+        let height = ctx.bounding_box.size().height() as usize;
+        let first = self.message_log.len().saturating_sub(height);
+        for (i, message) in self.message_log[first..].iter().enumerate() {
+            let colour = Rgba32::new_grey(if i + first + 1 == self.message_log.len() {
+                255
+            } else {
+                127
+            });
+            render_text_line(message, colour, Coord::new(0, i as i32), ctx, fb);
+        }
+    }
+}
+
+// Draw lines of text in a box at the top of the map, on either its left or right side. Lines which
+// don't fit in the box are cut off.
+fn render_debug_box(lines: &[String], on_right: bool, ctx: Ctx, fb: &mut FrameBuffer) {
+    let map_size = ctx.bounding_box.size();
+    let width =
+        (lines.iter().map(|line| line.len()).max().unwrap_or(0) as u32 + 2).min(map_size.width());
+    let height = (lines.len() as u32 + 2).min(map_size.height());
+    let x = if on_right {
+        (map_size.width() - width) as i32
+    } else {
+        0
+    };
+    let ctx = ctx
+        .add_offset(Coord::new(x, 0))
+        .set_size(Size::new(width, height))
+        .add_depth(20);
+    for coord in ctx.bounding_box.size().coord_iter_row_major() {
+        fb.set_cell_relative_to_ctx(
+            ctx,
+            coord,
+            0,
+            RenderCell::BLANK.with_background(Rgba32::new(0, 0, 0, 223)),
+        );
+    }
+    let colour = Rgba32::new_grey(255);
+    for (i, line) in lines.iter().enumerate() {
+        render_text_line(line, colour, Coord::new(1, i as i32 + 1), ctx, fb);
+    }
+}
+
+fn describe_shot_outcome(shot_outcome: ShotOutcome) -> String {
+    match shot_outcome {
+        ShotOutcome::Missed => "Your shot missed".to_string(),
+        ShotOutcome::Hit(tile) => format!("Your shot hit the {}", tile_name(tile)),
+        ShotOutcome::Killed(tile) => format!("Your shot killed the {}", tile_name(tile)),
+    }
+}
+
+// A name for each tile, for describing the world to the player
+fn tile_name(tile: Tile) -> &'static str {
+    match tile {
+        Tile::Player => "you",
+        Tile::Goblin => "goblin",
+        Tile::Projectile => "projectile",
+        Tile::Wall => "wall",
+        Tile::DoorOpen => "doorway",
+        Tile::DoorClosed => "door",
+        Tile::Floor => "floor",
+        Tile::CaveWall => "cave wall",
+        Tile::CaveFloor => "cave floor",
+        Tile::Grass => "grass",
+        Tile::GrassCrushed => "crushed grass",
+        Tile::Water => "water",
+        Tile::Glyph { name, .. } => name,
+    }
+}
+
+// A named unit type representing the side panel showing the player's status and what they can see
+struct HudComponent;
+
+impl HudComponent {
+    // Describe what the player is standing on, preferring features (e.g. doorways) to the floor
+    fn terrain_underfoot(cell: &VisibleCellData) -> &'static str {
+        cell.entity_data
+            .feature
+            .as_ref()
+            .or(cell.entity_data.floor.as_ref())
+            .map(|entity_data| tile_name(entity_data.tile))
+            .unwrap_or("nothing")
+    }
+
+    // Returns the names of all the creatures the player can currently see, other than the player
+    fn visible_creatures(game: &Game) -> Vec<&'static str> {
+        game.visibility_grid()
+            .enumerate()
+            .filter_map(|(_, visibility)| match visibility {
+                CellVisibility::Current { data, .. } => data.entity_data.character.as_ref(),
+                _ => None,
+            })
+            .filter(|entity_data| !matches!(entity_data.tile, Tile::Player))
+            .map(|entity_data| tile_name(entity_data.tile))
+            .collect()
+    }
+}
+
+impl Component for HudComponent {
+    type Output = ();
+    type State = Game;
+
+    fn render(&self, state: &Self::State, ctx: Ctx, fb: &mut FrameBuffer) {
+        let player_coord = state.get_player_coord();
+        let (light_colour, player_cell) = match state.visibility_grid().get_visibility(player_coord)
+        {
+            CellVisibility::Current { data, light_colour } => (light_colour, Some(data)),
+            _ => (None, None),
+        };
+        let health = state.player_health();
+        let light_percent = light_colour
+            .map(|colour| colour.max_channel() as u32 * 100 / 255)
+            .unwrap_or(0);
+        let mut lines = vec![
+            format!("Health: {}/{}", health.current, health.max),
+            format!("Turn: {}", state.turn_count()),
+            format!("Light: {}%", light_percent),
+            format!(
+                "Stealth: {}",
+                if state.is_player_concealed() {
+                    "hidden"
+                } else {
+                    "exposed"
+                }
+            ),
+            String::new(),
+            "Standing on:".to_string(),
+            format!(
+                " {}",
+                player_cell
+                    .map(Self::terrain_underfoot)
+                    .unwrap_or("nothing")
+            ),
+            String::new(),
+            "Seed:".to_string(),
+            format!(" {}", state.seed()),
+            String::new(),
+            // There are no stairs yet, so seeing the whole level is how the game is won
+            "Goal:".to_string(),
+            " explore the whole".to_string(),
+            " level to win".to_string(),
+            String::new(),
+            "In view:".to_string(),
+        ];
+        let visible_creatures = Self::visible_creatures(state);
+        if visible_creatures.is_empty() {
+            lines.push(" nothing".to_string());
+        } else {
+            lines.extend(visible_creatures.iter().map(|name| format!(" {}", name)));
+        }
+        let colour = Rgba32::new_grey(255);
+        for (i, line) in lines.iter().enumerate() {
+            render_text_line(line, colour, Coord::new(0, i as i32), ctx, fb);
+        }
+    }
+
+    fn update(&mut self, _state: &mut Self::State, _ctx: Ctx, _event: Event) -> Self::Output {}
+
+    fn size(&self, _state: &Self::State, ctx: Ctx) -> Size {
+        ctx.bounding_box.size()
+    }
+}
+
+// Why the player left the game screen
+enum GameScreenExit {
+    Outcome(GameOutcome),
+    // The player wants to replace the current level
+    Reset,
+    // The player wants to replace the current level with one generated from a seed of their
+    // choosing. Only available when debug commands are enabled.
+    ChooseSeed,
+}
+
 // A named unit type representing the renderable, interactive  game area
-struct GameComponent;
+struct GameComponent {
+    hud: HudComponent,
+}
 
 impl Component for GameComponent {
-    type Output = ();
+    type Output = Option<GameScreenExit>;
     type State = GameData;
 
     fn render(&self, state: &Self::State, ctx: Ctx, fb: &mut FrameBuffer) {
-        state.render(ctx, fb);
+        let layout = Layout::new(ctx.bounding_box.size());
+        state.render_map(layout.map_ctx(ctx), fb);
+        if let Some(side_panel_ctx) = layout.side_panel_ctx(ctx) {
+            self.hud.render(&state.game, side_panel_ctx, fb);
+        }
+        if let Some(message_log_ctx) = layout.message_log_ctx(ctx) {
+            state.render_message_log(message_log_ctx, fb);
+        }
     }
 
-    fn update(&mut self, state: &mut Self::State, _ctx: Ctx, event: Event) -> Self::Output {
+    fn update(&mut self, state: &mut Self::State, ctx: Ctx, event: Event) -> Self::Output {
         match event {
+            // The player can't act until their projectile lands
+            Event::Input(_) if state.game.is_projectile_in_flight() => (),
             Event::Input(input) => {
-                if let Some(KeyboardInput::Char('r')) = input.keyboard() {
-                    state.game.reset();
+                // Pressing any key stops the player travelling
+                if input.keyboard().is_some() {
+                    state.travel = None;
+                }
+                if let Some(target) = state.targeting {
+                    if let Some(targeting_action) = targeting_action_from_input(input) {
+                        state.handle_targeting_action(target, targeting_action);
+                    }
+                    return None;
+                }
+                if state.debug {
+                    if let Input::Mouse(MouseInput::MouseMove { coord, .. }) = input {
+                        let layout = Layout::new(ctx.bounding_box.size());
+                        state.update_cursor(coord, layout.map_ctx(ctx));
+                    }
+                    if let Some(KeyboardInput::Char('G')) = input.keyboard() {
+                        return Some(GameScreenExit::ChooseSeed);
+                    }
+                    if let Some(debug_action) = debug_action_from_input(input) {
+                        state.handle_debug_action(debug_action);
+                    }
+                }
+                match input.keyboard() {
+                    Some(KeyboardInput::Char('r')) => return Some(GameScreenExit::Reset),
+                    Some(KeyboardInput::Char('m')) => state.show_overview = !state.show_overview,
+                    Some(KeyboardInput::Char('f')) => state.start_targeting(),
+                    _ => (),
+                }
+                if let Input::Mouse(MouseInput::MousePress {
+                    button: MouseButton::Left,
+                    coord,
+                }) = input
+                {
+                    let layout = Layout::new(ctx.bounding_box.size());
+                    state.start_travel(coord, layout.map_ctx(ctx));
                 }
                 if let Some(game_action) = game_action_from_input(input) {
                     state.handle_game_action(game_action);
                 }
             }
-            Event::Tick(_) => state.game.animation_tick(),
+            Event::Tick(since_last_tick) => {
+                state.game.animation_tick();
+                if let Some(shot_outcome) = state.game.take_shot_outcome() {
+                    state.push_message(describe_shot_outcome(shot_outcome));
+                }
+                if !state.game.is_projectile_in_flight() {
+                    state.travel_tick(since_last_tick);
+                }
+            }
             _ => (),
         }
+        state.game.outcome().map(GameScreenExit::Outcome)
     }
 
     fn size(&self, _state: &Self::State, ctx: Ctx) -> Size {
@@ -216,18 +1089,651 @@ impl Component for GameComponent {
     }
 }
 
-pub fn app(config: Config) -> App {
-    // Instantiate the game state
-    let screen_size = Size::new(60, 45);
-    let game_data = GameData::new(screen_size, config);
-    cf(GameComponent)
-        .ignore_output() // Coerce the component's output type to `app::Output`.
-        .with_state(game_data) // Associate the game state with the component.
-        .exit_on_close() // Exit the program when its window is closed.
-        .catch_escape() // Catch the escape event so we can exit on escape.
-        .map(|res| match res {
-            Err(Escape) => app::Exit, // Exit the program when escape is pressed.
-            Ok(output) => output,     // Other outputs are simply returned.
+// The state of the whole app, shared between the menus and the game
+struct AppData {
+    world_size: Size,
+    // Used to start each new game
+    config: Config,
+    // The game in progress, if any
+    game_data: Option<GameData>,
+    // Whether debug commands are enabled in new games
+    debug: bool,
+}
+
+impl AppData {
+    fn new_game(&mut self) {
+        self.game_data = Some(GameData::new(
+            self.world_size,
+            self.config.clone(),
+            self.debug,
+        ));
+    }
+}
+
+// The game screen is only shown while there is a game in progress
+fn game_data(app_data: &AppData) -> &GameData {
+    app_data.game_data.as_ref().expect("no game in progress")
+}
+
+fn game_data_mut(app_data: &mut AppData) -> &mut GameData {
+    app_data.game_data.as_mut().expect("no game in progress")
+}
+
+// Draw the game in progress (if any)
+fn game_background() -> CF<(), AppData> {
+    render_state(|app_data: &AppData, ctx, fb| {
+        if let Some(game_data) = app_data.game_data.as_ref() {
+            GameComponent { hud: HudComponent }.render(game_data, ctx, fb);
+        }
+    })
+}
+
+// Draw a component over the game in progress, darkening the game so the component stands out
+fn over_game<T: 'static>(component: CF<T, AppData>) -> CF<T, AppData> {
+    component.overlay_tint(
+        game_background(),
+        |colour: Rgba32| {
+            colour
+                .to_rgb24()
+                .saturating_scalar_mul_div(1, 3)
+                .to_rgba32(255)
+        },
+        10,
+    )
+}
+
+// A menu item shown in yellow with a marker beside it when selected
+fn menu_item_identifier(text: &str) -> MenuItemIdentifierBoxed {
+    identifier::static_(
+        StyledString {
+            string: format!("> {}", text),
+            style: Style::plain_text()
+                .with_bold(true)
+                .with_foreground(Rgba32::new_rgb(255, 255, 0)),
+        },
+        StyledString {
+            string: format!("  {}", text),
+            style: Style::plain_text().with_foreground(Rgba32::new_grey(187)),
+        },
+    )
+}
+
+// Draw a component in a titled box. The title should be no wider than the component.
+fn titled_box<T: 'static>(component: CF<T, AppData>, title: &str) -> CF<T, AppData> {
+    component
+        .border(BorderStyle {
+            title: Some(title.to_string()),
+            padding: BorderPadding::all(1),
+            ..Default::default()
         })
+        .fill(Rgba32::new_grey(0))
+}
+
+#[derive(Clone, Copy)]
+enum MainMenuEntry {
+    NewGame,
+    Continue,
+    Options,
+    Quit,
+}
+
+// "Continue" is only offered when there is a game in progress
+fn main_menu() -> CF<Option<OrClose<MainMenuEntry>>, AppData> {
+    on_state_then(|app_data: &mut AppData| {
+        let mut builder = menu_builder();
+        if app_data.game_data.is_some() {
+            builder = builder.add_item(
+                item(MainMenuEntry::Continue, menu_item_identifier("Continue"))
+                    .add_hotkey_char('c'),
+            );
+        }
+        let menu = builder
+            .add_item(
+                item(MainMenuEntry::NewGame, menu_item_identifier("New game")).add_hotkey_char('n'),
+            )
+            .add_item(
+                item(MainMenuEntry::Options, menu_item_identifier("Options")).add_hotkey_char('o'),
+            )
+            .add_item(item(MainMenuEntry::Quit, menu_item_identifier("Quit")).add_hotkey_char('q'))
+            .build_cf();
+        let game_name = styled_string(
+            "Gridbugs Roguelike Tutorial".to_string(),
+            Style::plain_text()
+                .with_bold(true)
+                .with_foreground(Rgba32::new_grey(255)),
+        );
+        over_game(
+            titled_box(menu, "Menu")
+                .with_title(game_name, 1)
+                .fill(Rgba32::new_grey(0))
+                .centre()
+                .menu_harness(),
+        )
+    })
+}
+
+#[derive(Clone, Copy)]
+enum OptionsMenuEntry {
+    ToggleOmniscient,
+    Back,
+}
+
+// Options apply to games started after they are changed
+fn options_menu() -> CF<Option<OrClose<OptionsMenuEntry>>, AppData> {
+    on_state_then(|app_data: &mut AppData| {
+        let omniscient = format!(
+            "Omniscient (new games): {}",
+            if app_data.config.omniscient {
+                "on"
+            } else {
+                "off"
+            }
+        );
+        let menu = menu_builder()
+            .add_item(
+                item(
+                    OptionsMenuEntry::ToggleOmniscient,
+                    menu_item_identifier(&omniscient),
+                )
+                .add_hotkey_char('o'),
+            )
+            .add_item(
+                item(OptionsMenuEntry::Back, menu_item_identifier("Back")).add_hotkey_char('b'),
+            )
+            .build_cf();
+        over_game(titled_box(menu, "Options").centre().menu_harness())
+    })
+}
+
+#[derive(Clone, Copy)]
+enum PauseMenuEntry {
+    Resume,
+    MainMenu,
+    Quit,
+}
+
+// The game in progress stays in the app state while paused, so it can be resumed from here or
+// from the main menu
+fn pause_menu() -> CF<Option<OrClose<PauseMenuEntry>>, AppData> {
+    let menu = menu_builder()
+        .add_item(item(PauseMenuEntry::Resume, menu_item_identifier("Resume")).add_hotkey_char('r'))
+        .add_item(
+            item(PauseMenuEntry::MainMenu, menu_item_identifier("Main menu")).add_hotkey_char('m'),
+        )
+        .add_item(item(PauseMenuEntry::Quit, menu_item_identifier("Quit")).add_hotkey_char('q'))
+        .build_cf();
+    over_game(titled_box(menu, "Paused").centre().menu_harness())
+}
+
+#[derive(Clone)]
+enum ResetMenuEntry {
+    NewLevel,
+    PreviousLevel(Seed),
+    Cancel,
+}
+
+// Ask the player to confirm replacing the current level, either with a new level or with one they
+// have played before
+fn reset_menu() -> CF<Option<OrClose<ResetMenuEntry>>, AppData> {
+    on_state_then(|app_data: &mut AppData| {
+        let game = &game_data(app_data).game;
+        let mut builder = menu_builder().add_item(
+            item(ResetMenuEntry::NewLevel, menu_item_identifier("New level")).add_hotkey_char('y'),
+        );
+        // The number keys select previous levels, most recent first
+        for (i, seed) in game.seed_history().iter().rev().enumerate() {
+            let text = format!("{}. Previous level (seed {})", i + 1, seed);
+            let mut add_item = item(
+                ResetMenuEntry::PreviousLevel(seed.clone()),
+                menu_item_identifier(&text),
+            );
+            if let Some(digit) = std::char::from_digit(i as u32 + 1, 10) {
+                add_item = add_item.add_hotkey_char(digit);
+            }
+            builder = builder.add_item(add_item);
+        }
+        let menu = builder
+            .add_item(
+                item(ResetMenuEntry::Cancel, menu_item_identifier("Cancel")).add_hotkey_char('n'),
+            )
+            .build_cf();
+        let current_seed = styled_string(
+            format!("Current seed: {}", game.seed()),
+            Style::plain_text().with_foreground(Rgba32::new_grey(255)),
+        );
+        over_game(
+            titled_box(menu, "Reset level?")
+                .with_title(current_seed, 1)
+                .fill(Rgba32::new_grey(0))
+                .centre()
+                .menu_harness(),
+        )
+    })
+}
+
+// A prompt for the player to type in a seed, either as a number or a phrase
+#[derive(Default)]
+struct SeedPrompt {
+    text: String,
+}
+
+impl SeedPrompt {
+    // The longest seed which can be typed, leaving room for the text cursor
+    const WIDTH: u32 = 32;
+
+    // Seeds are made of letters, digits and the spaces or hyphens separating words
+    fn can_type(&self, ch: char) -> bool {
+        (ch.is_ascii_alphanumeric() || ch == ' ' || ch == '-')
+            && self.text.len() + 1 < Self::WIDTH as usize
+    }
+}
+
+impl Component for SeedPrompt {
+    type Output = Option<Seed>;
+    type State = ();
+
+    fn render(&self, _state: &Self::State, ctx: Ctx, fb: &mut FrameBuffer) {
+        let text = format!("{}_", self.text);
+        render_text_line(&text, Rgba32::new_grey(255), Coord::new(0, 0), ctx, fb);
+    }
+
+    fn update(&mut self, _state: &mut Self::State, _ctx: Ctx, event: Event) -> Self::Output {
+        match event.keyboard_input()? {
+            KeyboardInput::Char(ch) if self.can_type(ch) => self.text.push(ch),
+            keys::BACKSPACE | KeyboardInput::Delete => {
+                self.text.pop();
+            }
+            keys::RETURN => return self.text.parse().ok(),
+            _ => (),
+        }
+        None
+    }
+
+    fn size(&self, _state: &Self::State, _ctx: Ctx) -> Size {
+        Size::new(Self::WIDTH, 1)
+    }
+}
+
+// Ask the player for a seed to generate a new level from
+fn seed_prompt() -> CF<Option<OrEscape<Seed>>, AppData> {
+    over_game(
+        titled_box(cf(SeedPrompt::default()).ignore_state(), "Seed")
+            .centre()
+            .catch_escape(),
+    )
+}
+
+// Lines of text drawn one after another
+struct TextLines {
+    lines: Vec<String>,
+}
+
+impl Component for TextLines {
+    type Output = ();
+    type State = ();
+
+    fn render(&self, _state: &Self::State, ctx: Ctx, fb: &mut FrameBuffer) {
+        let colour = Rgba32::new_grey(255);
+        for (i, line) in self.lines.iter().enumerate() {
+            render_text_line(line, colour, Coord::new(0, i as i32), ctx, fb);
+        }
+    }
+
+    fn update(&mut self, _state: &mut Self::State, _ctx: Ctx, _event: Event) -> Self::Output {}
+
+    fn size(&self, _state: &Self::State, _ctx: Ctx) -> Size {
+        let width = self
+            .lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        Size::new(width as u32, self.lines.len() as u32)
+    }
+}
+
+// Summarise a game which has ended. The game is discarded once a key is pressed.
+fn game_over_screen(outcome: GameOutcome) -> CF<Option<()>, AppData> {
+    on_state_then(move |app_data: &mut AppData| {
+        let turn_count = game_data(app_data).game.turn_count();
+        let (title, summary) = match outcome {
+            GameOutcome::Defeat => ("Game over", "You died."),
+            GameOutcome::Victory => ("Victory", "You explored the entire level!"),
+        };
+        let text = TextLines {
+            lines: vec![
+                summary.to_string(),
+                format!("Turns taken: {}", turn_count),
+                String::new(),
+                "Press any key to continue".to_string(),
+            ],
+        };
+        over_game(titled_box(cf(text).ignore_state(), title).centre()).press_any_key()
+    })
+}
+
+// Which screen of the app is being shown
+enum AppScreen {
+    MainMenu,
+    Options,
+    Game,
+    Pause,
+    Reset,
+    ChooseSeed,
+    GameOver(GameOutcome),
+}
+
+fn app_screen(screen: AppScreen) -> CF<Option<LoopControl<AppScreen, app::Exit>>, AppData> {
+    use LoopControl::{Break, Continue};
+    match screen {
+        AppScreen::MainMenu => main_menu().map_side_effect(|entry, app_data| match entry {
+            Ok(MainMenuEntry::NewGame) => {
+                app_data.new_game();
+                Continue(AppScreen::Game)
+            }
+            Ok(MainMenuEntry::Continue) => Continue(AppScreen::Game),
+            Ok(MainMenuEntry::Options) => Continue(AppScreen::Options),
+            Ok(MainMenuEntry::Quit) | Err(Close) => Break(app::Exit),
+        }),
+        AppScreen::Options => options_menu().map_side_effect(|entry, app_data| match entry {
+            Ok(OptionsMenuEntry::ToggleOmniscient) => {
+                app_data.config.omniscient = !app_data.config.omniscient;
+                Continue(AppScreen::Options)
+            }
+            Ok(OptionsMenuEntry::Back) | Err(Close) => Continue(AppScreen::MainMenu),
+        }),
+        AppScreen::Game => cf(GameComponent { hud: HudComponent })
+            .lens_state(LensFns::new(game_data, game_data_mut))
+            .catch_escape()
+            .map_side_effect(|res, app_data| match res {
+                Ok(GameScreenExit::Outcome(outcome)) => {
+                    let game = &game_data(app_data).game;
+                    info!(
+                        "Game over with outcome {:?} after {} turns (seed {})",
+                        outcome,
+                        game.turn_count(),
+                        game.seed()
+                    );
+                    Continue(AppScreen::GameOver(outcome))
+                }
+                Ok(GameScreenExit::Reset) => Continue(AppScreen::Reset),
+                Ok(GameScreenExit::ChooseSeed) => Continue(AppScreen::ChooseSeed),
+                // Escape cancels aiming rather than pausing the game
+                Err(Escape) if game_data(app_data).targeting.is_some() => {
+                    game_data_mut(app_data).targeting = None;
+                    Continue(AppScreen::Game)
+                }
+                Err(Escape) => Continue(AppScreen::Pause),
+            }),
+        AppScreen::Pause => pause_menu().map(|entry| match entry {
+            Ok(PauseMenuEntry::Resume) | Err(Close) => Continue(AppScreen::Game),
+            Ok(PauseMenuEntry::MainMenu) => Continue(AppScreen::MainMenu),
+            Ok(PauseMenuEntry::Quit) => Break(app::Exit),
+        }),
+        AppScreen::Reset => reset_menu().map_side_effect(|entry, app_data| {
+            match entry {
+                Ok(ResetMenuEntry::NewLevel) => game_data_mut(app_data).reset(),
+                Ok(ResetMenuEntry::PreviousLevel(seed)) => {
+                    game_data_mut(app_data).regenerate_with_seed(seed)
+                }
+                Ok(ResetMenuEntry::Cancel) | Err(Close) => (),
+            }
+            Continue(AppScreen::Game)
+        }),
+        AppScreen::ChooseSeed => seed_prompt().map_side_effect(|res, app_data| {
+            if let Ok(seed) = res {
+                game_data_mut(app_data).regenerate_with_seed(seed);
+            }
+            Continue(AppScreen::Game)
+        }),
+        AppScreen::GameOver(outcome) => {
+            game_over_screen(outcome).map_side_effect(|(), app_data| {
+                app_data.game_data = None;
+                Continue(AppScreen::MainMenu)
+            })
+        }
+    }
+}
+
+// An update to the map being edited
+enum EditorAction {
+    MoveCursor(CardinalDirection),
+    Paint,
+    SelectBrush(MapCell),
+    PlacePlayerStart,
+    Save,
+    Exit,
+}
+
+// Associate editor actions with input events
+fn editor_action_from_input(input: Input) -> Option<EditorAction> {
+    match input {
+        Input::Keyboard(keyboard_input) => {
+            use CardinalDirection::*;
+            use EditorAction::*;
+            match keyboard_input {
+                KeyboardInput::Left => Some(MoveCursor(West)),
+                KeyboardInput::Right => Some(MoveCursor(East)),
+                KeyboardInput::Up => Some(MoveCursor(North)),
+                KeyboardInput::Down => Some(MoveCursor(South)),
+                KeyboardInput::Char(' ') => Some(Paint),
+                KeyboardInput::Char('p') => Some(PlacePlayerStart),
+                KeyboardInput::Char('s') => Some(Save),
+                keys::ESCAPE => Some(Exit),
+                // The number keys select brushes in the order they appear in `MapCell::ALL`
+                KeyboardInput::Char(ch) => ch
+                    .to_digit(10)
+                    .and_then(|digit| MapCell::ALL.get((digit as usize).checked_sub(1)?))
+                    .map(|&cell| SelectBrush(cell)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+// The state of the level editor
+struct EditorData {
+    map: MapFile,
+    // Where the map will be saved
+    path: PathBuf,
+    // The map as it will appear to a player, with every cell visible
+    visibility_grid: VisibilityGrid<VisibleCellData>,
+    cursor: Coord,
+    brush: MapCell,
+    // Shown in the status bar, and replaced with the result of saving the map
+    message: String,
+    // True iff the map has changed since it was last saved
+    unsaved_changes: bool,
+    // True while asking whether to save unsaved changes before exiting
+    confirming_exit: bool,
+}
+
+impl EditorData {
+    fn new(map: MapFile, path: PathBuf) -> Self {
+        let cursor = (map.size() / 2).to_coord().unwrap();
+        let mut self_ = Self {
+            visibility_grid: VisibilityGrid::new(map.size()),
+            map,
+            path,
+            cursor,
+            brush: MapCell::Wall,
+            message: "space:paint 1-7:brush p:start s:save esc:quit".to_string(),
+            unsaved_changes: false,
+            confirming_exit: false,
+        };
+        self_.update_visibility_grid();
+        self_
+    }
+
+    // Build a world from the map so it can be drawn with the same renderer as the game
+    fn update_visibility_grid(&mut self) {
+        // Use the same rng each time so water doesn't change colour with every edit
+        let mut rngs = RngStreams::new(0);
+        match self.map.to_terrain(&mut rngs) {
+            Ok(Terrain { world, .. }) => {
+                self.visibility_grid = omniscient_visibility_grid(&world);
+            }
+            Err(e) => {
+                warn!("Failed to draw map: {}", e);
+                self.message = format!("Failed to draw map: {}", e);
+            }
+        }
+    }
+
+    fn save(&mut self) {
+        self.message = match self.map.save(&self.path) {
+            Ok(()) => {
+                info!("Saved map to {}", self.path.display());
+                self.unsaved_changes = false;
+                format!("saved to {}", self.path.display())
+            }
+            Err(e) => {
+                warn!("Failed to save map to {}: {}", self.path.display(), e);
+                e
+            }
+        };
+    }
+
+    // Returns `Some(app::Exit)` iff the editor should close
+    fn handle_editor_action(&mut self, editor_action: EditorAction) -> Option<app::Exit> {
+        match editor_action {
+            EditorAction::MoveCursor(direction) => {
+                let cursor = self.cursor + direction.coord();
+                if cursor.is_valid(self.map.size()) {
+                    self.cursor = cursor;
+                }
+            }
+            EditorAction::Paint => match self.map.set_cell(self.cursor, self.brush) {
+                Ok(()) => {
+                    self.unsaved_changes = true;
+                    self.update_visibility_grid();
+                }
+                Err(e) => self.message = e,
+            },
+            EditorAction::SelectBrush(brush) => self.brush = brush,
+            EditorAction::PlacePlayerStart => {
+                self.map.set_player_start(self.cursor);
+                self.unsaved_changes = true;
+                self.update_visibility_grid();
+            }
+            EditorAction::Save => self.save(),
+            EditorAction::Exit => {
+                if !self.unsaved_changes {
+                    return Some(app::Exit);
+                }
+                self.confirming_exit = true;
+                self.message = "save before quitting? s:save d:discard other:cancel".to_string();
+            }
+        }
+        None
+    }
+
+    // Handle the answer to whether unsaved changes should be saved before exiting. If saving
+    // fails the editor stays open so the changes aren't lost.
+    fn handle_exit_confirmation(&mut self, keyboard_input: KeyboardInput) -> Option<app::Exit> {
+        self.confirming_exit = false;
+        match keyboard_input {
+            KeyboardInput::Char('s') => {
+                self.save();
+                if self.unsaved_changes {
+                    None
+                } else {
+                    Some(app::Exit)
+                }
+            }
+            KeyboardInput::Char('d') => {
+                info!("Discarded unsaved changes to {}", self.path.display());
+                Some(app::Exit)
+            }
+            _ => {
+                self.message = "kept editing".to_string();
+                None
+            }
+        }
+    }
+
+    fn render(&self, ctx: Ctx, fb: &mut FrameBuffer) {
+        // The bottom row of the screen is left for the status bar
+        let status_bar_y = ctx.bounding_box.size().height().saturating_sub(1);
+        let map_ctx = ctx.set_height(status_bar_y);
+        // The camera follows the cursor
+        let offset = camera_offset(self.cursor, self.map.size(), map_ctx.bounding_box.size());
+        VisibilityGridRenderer {
+            visibility_grid: &self.visibility_grid,
+            offset,
+        }
+        .render(map_ctx, fb);
+        fb.set_cell_relative_to_ctx(
+            map_ctx,
+            self.cursor - offset,
+            10,
+            RenderCell::BLANK.with_background(Rgba32::new(255, 255, 0, 127)),
+        );
+        let status_bar = StyledString {
+            string: format!(
+                "{}{} | {}",
+                self.brush.name(),
+                if self.unsaved_changes {
+                    " (unsaved)"
+                } else {
+                    ""
+                },
+                self.message
+            ),
+            style: Style::plain_text().with_background(Rgba32::new_grey(0)),
+        };
+        status_bar.render(&(), ctx.add_y(status_bar_y as i32).set_height(1), fb);
+    }
+}
+
+// A named unit type representing the level editor
+struct EditorComponent;
+
+impl Component for EditorComponent {
+    type Output = app::Output;
+    type State = EditorData;
+
+    fn render(&self, state: &Self::State, ctx: Ctx, fb: &mut FrameBuffer) {
+        state.render(ctx, fb);
+    }
+
+    fn update(&mut self, state: &mut Self::State, _ctx: Ctx, event: Event) -> Self::Output {
+        if state.confirming_exit {
+            return state.handle_exit_confirmation(event.keyboard_input()?);
+        }
+        let editor_action = editor_action_from_input(event.input()?)?;
+        state.handle_editor_action(editor_action)
+    }
+
+    fn size(&self, _state: &Self::State, ctx: Ctx) -> Size {
+        ctx.bounding_box.size()
+    }
+}
+
+// The default size of the world, which is also the size of the screen. Larger worlds scroll to
+// keep the player in view.
+pub const WORLD_SIZE: Size = Size::new_u16(60, 45);
+
+pub fn app(world_size: Size, config: Config, debug: bool) -> App {
+    let app_data = AppData {
+        world_size,
+        config,
+        game_data: None,
+        debug,
+    };
+    // Start at the main menu, and move between screens until the player quits
+    loop_(AppScreen::MainMenu, app_screen)
+        .with_state(app_data) // Associate the app state with the screens.
+        .exit_on_close() // Exit the program when its window is closed.
+        .clear_each_frame()
+}
+
+// An app for editing a map which will be saved to the given path
+pub fn editor_app(map: MapFile, path: PathBuf) -> App {
+    let editor_data = EditorData::new(map, path);
+    cf(EditorComponent)
+        .with_state(editor_data)
+        .exit_on_close()
         .clear_each_frame()
 }
@@ -0,0 +1,131 @@
+use crate::{
+    app::VisibilityGridRenderer, game::Game, CELL_SIZE_PX, FONT_BYTES_BOLD, FONT_BYTES_NORMAL,
+};
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont};
+use gridbugs::chargrid::prelude::*;
+use std::{
+    fs::File,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+};
+
+// Which part of the level to include in an exported image
+#[derive(Clone, Copy, Debug)]
+pub enum ExportMode {
+    // Every cell of the level, regardless of what the player has seen
+    WholeLevel,
+    // Only the cells the player has seen, rendered as they appear on screen
+    Remembered,
+}
+
+// Returns a path in the current directory to export the current level to, derived from the seed
+pub fn default_path(game: &Game, mode: ExportMode) -> PathBuf {
+    let suffix = match mode {
+        ExportMode::WholeLevel => "level",
+        ExportMode::Remembered => "remembered",
+    };
+    PathBuf::from(format!("map-{}-{}.png", game.seed(), suffix))
+}
+
+// Render the map into a frame buffer the size of the level, using the same renderer as the game
+fn render_to_frame_buffer(game: &Game, mode: ExportMode) -> FrameBuffer {
+    let omniscient_visibility_grid;
+    let visibility_grid = match mode {
+        ExportMode::WholeLevel => {
+            omniscient_visibility_grid = game.omniscient_visibility_grid();
+            &omniscient_visibility_grid
+        }
+        ExportMode::Remembered => game.visibility_grid(),
+    };
+    let mut fb = FrameBuffer::new(game.world_size());
+    let ctx = fb.default_ctx();
+    VisibilityGridRenderer {
+        visibility_grid,
+        offset: Coord::new(0, 0),
+    }
+    .render(ctx, &mut fb);
+    fb
+}
+
+// A buffer of RGB pixels
+struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; (width * height * 3) as usize],
+        }
+    }
+
+    // Blend a colour into the pixel at the given position, weighted by `alpha` (between 0 and 1)
+    fn blend_pixel(&mut self, x: u32, y: u32, colour: Rgba32, alpha: f32) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let index = ((y * self.width + x) * 3) as usize;
+        let alpha = alpha.clamp(0., 1.);
+        for (channel, value) in self.pixels[index..(index + 3)]
+            .iter_mut()
+            .zip([colour.r, colour.g, colour.b])
+        {
+            *channel = (*channel as f32 * (1. - alpha) + value as f32 * alpha).round() as u8;
+        }
+    }
+}
+
+// Draw each cell of a frame buffer into an image using the bundled fonts
+fn rasterise(fb: &FrameBuffer) -> Image {
+    let normal = FontRef::try_from_slice(FONT_BYTES_NORMAL).expect("failed to load font");
+    let bold = FontRef::try_from_slice(FONT_BYTES_BOLD).expect("failed to load font");
+    let cell_size_px = CELL_SIZE_PX as u32;
+    let scale = PxScale::from(CELL_SIZE_PX as f32);
+    let size = fb.size();
+    let mut image = Image::new(size.width() * cell_size_px, size.height() * cell_size_px);
+    for (coord, cell) in fb.enumerate() {
+        let left = coord.x as u32 * cell_size_px;
+        let top = coord.y as u32 * cell_size_px;
+        for y in 0..cell_size_px {
+            for x in 0..cell_size_px {
+                image.blend_pixel(left + x, top + y, cell.background, 1.);
+            }
+        }
+        let font = if cell.bold { &bold } else { &normal };
+        let ascent = font.as_scaled(scale).ascent();
+        let glyph = font
+            .glyph_id(cell.character)
+            .with_scale_and_position(scale, (left as f32, top as f32 + ascent));
+        if let Some(outlined_glyph) = font.outline_glyph(glyph) {
+            let bounds = outlined_glyph.px_bounds();
+            outlined_glyph.draw(|x, y, coverage| {
+                let x = bounds.min.x as i32 + x as i32;
+                let y = bounds.min.y as i32 + y as i32;
+                if x >= 0 && y >= 0 {
+                    image.blend_pixel(x as u32, y as u32, cell.foreground, coverage);
+                }
+            });
+        }
+    }
+    image
+}
+
+// Write an image of the current level to a PNG file
+pub fn export_png<P: AsRef<Path>>(game: &Game, mode: ExportMode, path: P) -> io::Result<()> {
+    let fb = render_to_frame_buffer(game, mode);
+    let image = rasterise(&fb);
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), image.width, image.height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&image.pixels)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test;
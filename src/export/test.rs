@@ -0,0 +1,36 @@
+use super::{export_png, ExportMode};
+use crate::{
+    game::{Config, Game},
+    seed::Seed,
+    CELL_SIZE_PX,
+};
+use gridbugs::coord_2d::Size;
+use std::fs::{self, File};
+
+#[test]
+fn exported_images_cover_the_whole_level() {
+    let size = Size::new(30, 20);
+    let config = Config {
+        omniscient: false,
+        rng_seed: Some(Seed::Number(1)),
+        generator: Default::default(),
+        generation_params: Default::default(),
+        map: None,
+    };
+    let game = Game::new(size, config);
+    for mode in [ExportMode::WholeLevel, ExportMode::Remembered] {
+        let path = std::env::temp_dir().join(format!(
+            "roguelike-export-test-{}-{:?}.png",
+            std::process::id(),
+            mode
+        ));
+        export_png(&game, mode, &path).unwrap();
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let reader = decoder.read_info().unwrap();
+        let (width, height) = reader.info().size();
+        fs::remove_file(&path).unwrap();
+        let cell_size_px = CELL_SIZE_PX as u32;
+        assert_eq!(width, size.width() * cell_size_px);
+        assert_eq!(height, size.height() * cell_size_px);
+    }
+}
@@ -1,7 +1,13 @@
 use crate::{
+    map_file::MapFile,
     realtime::{types as realtime_types, RealtimeComponents, RealtimeContext},
-    terrain::Terrain,
+    rng::{RngStream, RngStreams},
+    seed::Seed,
+    terrain::{GenerationParams, GeneratorChoice, Terrain},
 };
+use ai::Ai;
+use distance_map::DistanceMapCache;
+use environment::Environment;
 use gridbugs::{
     coord_2d::{Coord, Size},
     direction::{CardinalDirection, Direction},
@@ -10,12 +16,19 @@ use gridbugs::{
     rgb_int::Rgb24,
     spatial_table,
     visible_area_detection::{
-        vision_distance, CellVisibility, Light, Rational, VisibilityGrid, World as VisibleWorld,
+        vision_distance, CellVisibility, Light, VisibilityGrid, World as VisibleWorld,
     },
 };
+use log::{debug, info, warn};
+use perception::Perception;
 use rand::{Rng, SeedableRng};
 use rand_isaac::Isaac64Rng;
-use std::time::Duration;
+use ranged::ProjectileInFlight;
+pub use ranged::ShotOutcome;
+use std::{fmt, mem, time::Duration};
+use stealth::{Noise, CONCEALED_SPOT_DISTANCE_SQUARED, DOOR_NOISE, GRASS_NOISE, WATER_NOISE};
+use template::Animation;
+pub use template::EntityTemplates;
 
 const FRAME_DURATION: Duration = Duration::from_micros(1_000_000 / 60);
 
@@ -34,6 +47,8 @@ pub enum GrassState {
 #[derive(Clone, Copy, Debug)]
 pub enum Tile {
     Player,
+    Goblin,
+    Projectile,
     Wall,
     DoorOpen,
     DoorClosed,
@@ -43,17 +58,30 @@ pub enum Tile {
     Grass,
     GrassCrushed,
     Water,
+    // A tile described entirely by its entity template, so new kinds of entity can be added
+    // without changing the code
+    Glyph {
+        character: char,
+        colour: Rgb24,
+        name: &'static str,
+    },
 }
 
 impl Tile {
     pub fn is_wall(&self) -> bool {
-        match self {
-            Self::Wall | Self::DoorClosed | Self::DoorOpen | Self::CaveWall => true,
-            _ => false,
-        }
+        matches!(
+            self,
+            Self::Wall | Self::DoorClosed | Self::DoorOpen | Self::CaveWall
+        )
     }
 }
 
+#[derive(Clone, Copy, Debug)]
+pub struct Health {
+    pub current: u32,
+    pub max: u32,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct ColourHint {
     pub foreground: Rgb24,
@@ -79,13 +107,24 @@ entity_table::declare_entity_module! {
         grass_state: GrassState,
         realtime: (),
         colour_hint: ColourHint,
-
+        health: Health,
+        ai: Ai,
+        perception: Perception,
     }
 }
 use components::{Components, EntityData, EntityUpdate};
 
+mod ai;
+mod distance_map;
+mod environment;
+mod perception;
+mod ranged;
+mod stealth;
+mod template;
+
 spatial_table::declare_layers_module! {
     layers {
+        projectile: Projectile,
         character: Character,
         feature: Feature,
         floor: Floor,
@@ -97,14 +136,58 @@ use layers::{LayerTable, Layers};
 type SpatialTable = spatial_table::SpatialTable<Layers>;
 type Location = spatial_table::Location<Layer>;
 
+fn format_rgb24(colour: Rgb24) -> String {
+    format!("({}, {}, {})", colour.r, colour.g, colour.b)
+}
+
+// An entity and a human-readable description of each of its components, for debugging
+pub struct EntityDescription {
+    pub entity: Entity,
+    pub layer: Layer,
+    pub components: Vec<String>,
+}
+
 const PLAYER_VISION_DISTANCE: vision_distance::Circle = vision_distance::Circle::new_squared(1000);
 
+// Why an entity couldn't be added to the world
+#[derive(Clone, Debug)]
+pub enum SpawnError {
+    UnknownTemplate(String),
+    OutOfBounds(Coord),
+    LayerOccupied {
+        layer: Layer,
+        coord: Coord,
+        occupant: Entity,
+    },
+}
+
+impl fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownTemplate(name) => write!(f, "no entity template named {}", name),
+            Self::OutOfBounds(coord) => write!(f, "{:?} is outside the world", coord),
+            Self::LayerOccupied {
+                layer,
+                coord,
+                occupant,
+            } => write!(
+                f,
+                "there is already a {:?} ({:?}) at {:?}",
+                layer, occupant, coord
+            ),
+        }
+    }
+}
+
 // The state of the game's world
 pub struct World {
     components: Components, // the components of each entity in the world
     entity_allocator: EntityAllocator, // used to allocate new entities
     spatial_table: SpatialTable,
     realtime_components: RealtimeComponents,
+    templates: &'static EntityTemplates, // describes each kind of entity which can be spawned
+    environment: Environment,            // slow changes to the level such as grass regrowing
+    distance_map_cache: DistanceMapCache, // paths to places which have recently been travelled to
 }
 
 impl World {
@@ -118,6 +201,9 @@ impl World {
             spatial_table,
             entity_allocator,
             realtime_components,
+            templates: EntityTemplates::current(),
+            environment: Environment::default(),
+            distance_map_cache: DistanceMapCache::default(),
         }
     }
 
@@ -129,110 +215,173 @@ impl World {
         &mut self.components
     }
 
-    pub fn realtime_entities(&self) -> Entities {
+    pub fn realtime_entities(&self) -> Entities<'_> {
         self.components.realtime.entities()
     }
 
+    // Describe each component of an entity, including the state of its realtime components
+    fn describe_components(&self, entity: Entity) -> Vec<String> {
+        // Destructured so that adding a component without describing it is a compile error
+        let EntityData {
+            tile,
+            solid,
+            door_state,
+            opacity,
+            light,
+            grass_state,
+            realtime,
+            colour_hint,
+            health,
+            ai,
+            perception,
+        } = self.components.clone_entity_data(entity);
+        let mut descriptions = Vec::new();
+        if let Some(tile) = tile {
+            descriptions.push(format!("tile: {:?}", tile));
+        }
+        if solid.is_some() {
+            descriptions.push("solid".to_string());
+        }
+        if let Some(door_state) = door_state {
+            descriptions.push(format!("door_state: {:?}", door_state));
+        }
+        if let Some(opacity) = opacity {
+            descriptions.push(format!("opacity: {}", opacity));
+        }
+        if let Some(light) = light {
+            descriptions.push(format!(
+                "light: colour {}, distance^2 {}, diminish {}/{}",
+                format_rgb24(light.colour),
+                light.vision_distance.distance_squared(),
+                light.diminish.numerator,
+                light.diminish.denominator
+            ));
+        }
+        if let Some(grass_state) = grass_state {
+            descriptions.push(format!("grass_state: {:?}", grass_state));
+        }
+        if realtime.is_some() {
+            descriptions.push("realtime".to_string());
+        }
+        if let Some(colour_hint) = colour_hint {
+            descriptions.push(format!(
+                "colour_hint: fg {}, bg {}",
+                format_rgb24(colour_hint.foreground),
+                format_rgb24(colour_hint.background)
+            ));
+        }
+        if let Some(health) = health {
+            descriptions.push(format!("health: {}/{}", health.current, health.max));
+        }
+        if let Some(ai) = ai {
+            descriptions.push(format!("ai: {:?}", ai));
+        }
+        if let Some(perception) = perception {
+            descriptions.push(format!(
+                "perception: distance^2 {}",
+                perception.vision_distance().distance_squared()
+            ));
+        }
+        // The state of realtime components is mostly an rng, so only the schedule is described
+        if let Some(water_animation) = self
+            .realtime_components
+            .water_animation
+            .get_with_schedule(entity)
+        {
+            descriptions.push(format!(
+                "water_animation: next tick in {}ms",
+                water_animation.until_next_tick.as_millis()
+            ));
+        }
+        if let Some(projectile) = self.realtime_components.projectile.get(entity) {
+            descriptions.push(format!(
+                "projectile: {} cells left",
+                projectile.cells_remaining()
+            ));
+        }
+        descriptions
+    }
+
     // Helper method to spawn an entity at a location
-    fn spawn_entity<L: Into<Location>>(&mut self, location: L, entity_data: EntityData) -> Entity {
+    fn spawn_entity<L: Into<Location>>(
+        &mut self,
+        location: L,
+        entity_data: EntityData,
+    ) -> Result<Entity, SpawnError> {
         let entity = self.entity_allocator.alloc();
         let location @ Location { layer, coord } = location.into();
         if let Err(e) = self.spatial_table.update(entity, location) {
-            panic!("{:?}: There is already a {:?} at {:?}", e, layer, coord);
+            self.entity_allocator.free(entity);
+            return Err(match (e, layer) {
+                (spatial_table::UpdateError::OccupiedBy(occupant), Some(layer)) => {
+                    SpawnError::LayerOccupied {
+                        layer,
+                        coord,
+                        occupant,
+                    }
+                }
+                _ => SpawnError::OutOfBounds(coord),
+            });
         }
         self.components.insert_entity_data(entity, entity_data);
-        entity
+        Ok(entity)
     }
 
-    // Add a new entity representing the player character at the given coord
-    pub fn spawn_player(&mut self, coord: Coord) -> Entity {
-        self.spawn_entity(
-            (coord, Layer::Character),
-            entity_data! {
-                tile: Tile::Player,
-                light: Light {
-                    colour: Rgb24::new_grey(255),
-                    vision_distance: PLAYER_VISION_DISTANCE,
-                    diminish: Rational { numerator: 1, denominator: 150 },
-                }
-            },
-        )
-    }
-
-    pub fn spawn_wall(&mut self, coord: Coord) {
-        self.spawn_entity(
-            (coord, Layer::Feature),
-            entity_data! {
-                tile: Tile::Wall,
-                solid: (),
-                opacity: 255,
-            },
-        );
-    }
-
-    pub fn spawn_cave_wall(&mut self, coord: Coord) {
-        self.spawn_entity(
-            (coord, Layer::Feature),
-            entity_data! {
-                tile: Tile::CaveWall,
-                solid: (),
-                opacity: 255,
-            },
-        );
-    }
-
-    pub fn spawn_door(&mut self, coord: Coord) {
-        self.spawn_entity(
-            (coord, Layer::Feature),
-            entity_data! {
-                tile: Tile::DoorClosed,
-                door_state: DoorState::Closed,
-                solid: (),
-                opacity: 255,
-            },
-        );
-    }
-
-    pub fn spawn_floor(&mut self, coord: Coord) {
-        self.spawn_entity(
-            (coord, Layer::Floor),
-            entity_data! {
-                tile: Tile::Floor,
-            },
-        );
+    // Add a new entity at the given coord, with components described by the named template
+    pub fn spawn_from_template<R: Rng>(
+        &mut self,
+        name: &str,
+        coord: Coord,
+        rng: &mut R,
+    ) -> Result<Entity, SpawnError> {
+        let template = self
+            .templates
+            .get(name)
+            .ok_or_else(|| SpawnError::UnknownTemplate(name.to_string()))?;
+        let entity = self.spawn_entity((coord, template.layer), template.entity_data.clone())?;
+        self.insert_animation(entity, template.animation, rng);
+        if let Some(vision_distance) = template.vision_distance {
+            let perception = Perception::new(vision_distance, self.spatial_table.grid_size());
+            self.components.perception.insert(entity, perception);
+        }
+        Ok(entity)
     }
 
-    pub fn spawn_cave_floor(&mut self, coord: Coord) {
-        self.spawn_entity(
-            (coord, Layer::Floor),
-            entity_data! {
-                tile: Tile::CaveFloor,
-            },
-        );
+    // Remove an entity and all of its components from the world
+    pub fn remove_entity(&mut self, entity: Entity) {
+        self.spatial_table.remove(entity);
+        self.components.remove_entity(entity);
+        self.realtime_components.remove_entity(entity);
+        self.entity_allocator.free(entity);
     }
 
-    pub fn spawn_water<R: Rng>(&mut self, coord: Coord, rng: &mut R) {
-        let entity = self.spawn_entity(
-            (coord, Layer::Floor),
-            entity_data! {
-                tile: Tile::Water,
-                realtime: (),
-            },
-        );
-        self.realtime_components
-            .water_animation
-            .insert(entity, realtime_types::WaterAnimationState::new(rng));
+    // Move an entity to a new coord in its current layer
+    pub fn move_entity(&mut self, entity: Entity, coord: Coord) {
+        if let Err(e) = self.spatial_table.update_coord(entity, coord) {
+            warn!("Failed to move {:?} to {:?}: {:?}", entity, coord, e);
+        }
     }
 
-    pub fn spawn_grass(&mut self, coord: Coord) {
-        self.spawn_entity(
-            (coord, Layer::Feature),
-            entity_data! {
-                tile: Tile::Grass,
-                opacity: 128,
-                grass_state: GrassState::Normal,
-            },
-        );
+    fn insert_animation<R: Rng>(
+        &mut self,
+        entity: Entity,
+        animation: Option<Animation>,
+        rng: &mut R,
+    ) {
+        match animation {
+            Some(Animation::Water) => {
+                // Water gets its colour from the animation, but needs a colour before the
+                // animation first runs
+                self.components.colour_hint.insert(
+                    entity,
+                    realtime_types::WaterAnimationState::initial_colour_hint(rng),
+                );
+                self.realtime_components
+                    .water_animation
+                    .insert(entity, realtime_types::WaterAnimationState::new(rng));
+            }
+            None => (),
+        }
     }
 }
 
@@ -270,6 +419,9 @@ impl VisibleWorld for World {
 pub struct VisibleEntityData {
     pub tile: Tile,
     pub colour_hint: Option<ColourHint>,
+    // Whether the entity blocks movement and projectiles, and how much it blocks light
+    pub solid: bool,
+    pub opacity: u8,
 }
 
 #[derive(Default)]
@@ -278,107 +430,298 @@ pub struct VisibleCellData {
 }
 
 impl VisibleCellData {
-    fn update(&mut self, world: &World, coord: Coord) {
+    // Record what can be seen at a coord by a viewer at the given coord. Characters hidden in grass
+    // can't be seen unless the viewer is close by. Passing `None` as the viewer reveals everything.
+    fn update(&mut self, world: &World, coord: Coord, viewer: Option<Coord>) {
         let layers = world.spatial_table.layers_at_checked(coord);
         self.entity_data = layers.option_and_then(|&entity| {
             let maybe_tile = world.components.tile.get(entity).cloned();
             maybe_tile.map(|tile| VisibleEntityData {
                 tile,
                 colour_hint: world.components.colour_hint.get(entity).cloned(),
+                solid: world.components.solid.contains(entity),
+                opacity: world.components.opacity.get(entity).cloned().unwrap_or(0),
             })
         });
+        if let Some(viewer) = viewer {
+            if world.is_concealed_at(coord)
+                && (coord - viewer).magnitude2() > CONCEALED_SPOT_DISTANCE_SQUARED
+            {
+                self.entity_data.character = None;
+            }
+        }
     }
 }
 
+/// Returns true iff a wall is known to be at the given coord according to a visibility grid
+pub fn is_wall_known_at(visibility_grid: &VisibilityGrid<VisibleCellData>, coord: Coord) -> bool {
+    if let Some(data) = visibility_grid.get_data(coord) {
+        data.entity_data
+            .feature
+            .as_ref()
+            .map(|entity_data| entity_data.tile.is_wall())
+            .unwrap_or(false)
+    } else {
+        false
+    }
+}
+
+/// Returns a visibility grid in which every cell of the world is visible
+pub fn omniscient_visibility_grid(world: &World) -> VisibilityGrid<VisibleCellData> {
+    let mut visibility_grid = VisibilityGrid::new(world.spatial_table.grid_size());
+    visibility_grid.update_omniscient_custom(
+        Rgb24::new_grey(255),
+        world,
+        |data: &mut VisibleCellData, coord| data.update(world, coord, None),
+    );
+    visibility_grid
+}
+
+#[derive(Clone)]
 pub struct Config {
     pub omniscient: bool,
-    pub rng_seed: Option<u64>,
+    // The seed of the first level, or `None` to choose a random seed
+    pub rng_seed: Option<Seed>,
+    pub generator: GeneratorChoice,
+    pub generation_params: GenerationParams,
+    // If set, the level is loaded from this map rather than being generated
+    pub map: Option<MapFile>,
+}
+
+// How a game ended
+#[derive(Clone, Copy, Debug)]
+pub enum GameOutcome {
+    // The player ran out of health
+    Defeat,
+    // The player saw every non-wall cell of the level
+    Victory,
 }
 
-// Initialize a random number generator from a given seed, printing out the seed to help with
-// debugging
-fn rng_from_seed(seed: u64) -> Isaac64Rng {
-    println!("Seed: {}", seed);
-    Isaac64Rng::seed_from_u64(seed)
+// Create the terrain for a new level, either by loading it from the map in the config or by
+// generating it. If that fails, the level is an empty room instead so the game can continue.
+fn make_terrain(world_size: Size, config: &Config, rngs: &mut RngStreams) -> Terrain {
+    let terrain = match config.map.as_ref() {
+        Some(map) => {
+            info!(
+                "Loading level from map ({}x{})",
+                map.size().width(),
+                map.size().height()
+            );
+            map.to_terrain(rngs)
+        }
+        None => Terrain::generate(
+            world_size,
+            config.generator,
+            &config.generation_params,
+            rngs,
+        ),
+    };
+    let mut terrain = terrain.unwrap_or_else(|e| {
+        warn!(
+            "Failed to create level ({}), using an empty room instead",
+            e
+        );
+        MapFile::new(world_size)
+            .to_terrain(rngs)
+            .expect("failed to create empty room")
+    });
+    terrain
+        .world
+        .init_environment(rngs.get(RngStream::Environment));
+    terrain
+}
+
+// The number of previous levels whose seeds are remembered
+const MAX_SEED_HISTORY: usize = 9;
+
+// A level the player has left. Returning to its seed restores the level as it was left, so grass
+// which has regrown and water which has risen since it was generated aren't lost.
+struct SavedLevel {
+    seed: Seed,
+    world: World,
+    player_entity: Entity,
+    visibility_grid: VisibilityGrid<VisibleCellData>,
+    rngs: RngStreams,
+    turn_count: u64,
 }
 
 // The state of the game
 pub struct Game {
     world: World,
     player_entity: Entity,
+    // What the player has seen of the level
     visibility_grid: VisibilityGrid<VisibleCellData>,
+    // While the player is omniscient, the entire level is shown in place of what they have seen
+    omniscient_view: Option<VisibilityGrid<VisibleCellData>>,
     config: Config,
-    rng: Isaac64Rng,
+    seed: Seed,
+    // Seeds of previously generated levels, most recent last. Doesn't contain the current seed.
+    seed_history: Vec<Seed>,
+    // The levels whose seeds are in `seed_history`
+    saved_levels: Vec<SavedLevel>,
+    // Random number generators for the current level, all derived from its seed
+    rngs: RngStreams,
     animation_context: AnimationContext,
-    animation_rng: Isaac64Rng,
+    // Number of turns the player has taken on the current level
+    turn_count: u64,
+    // The player's turn doesn't end until their projectile lands
+    projectile_in_flight: Option<ProjectileInFlight>,
+    // What the player's last projectile did, until it has been reported to the player
+    shot_outcome: Option<ShotOutcome>,
+    // How the game ended, updated at the end of each of the player's turns
+    outcome: Option<GameOutcome>,
 }
 
 impl Game {
     pub fn new(world_size: Size, config: Config) -> Self {
-        let seed = match config.rng_seed {
-            None => {
-                let mut rng = Isaac64Rng::from_entropy();
-                rng.gen()
-            }
+        let seed = match config.rng_seed.clone() {
+            None => Seed::random_phrase(&mut Isaac64Rng::from_entropy()),
             Some(seed) => seed,
         };
-        let mut rng = rng_from_seed(seed);
+        info!("Starting a new game with seed {}", seed);
+        let mut rngs = RngStreams::new(seed.value());
         let Terrain {
             world,
             player_entity,
-        } = Terrain::generate(world_size, &mut rng);
-        let visibility_grid = VisibilityGrid::new(world_size);
+        } = make_terrain(world_size, &config, &mut rngs);
+        // The world may be a different size from the one requested if it was loaded from a map
+        let visibility_grid = VisibilityGrid::new(world.spatial_table.grid_size());
+        let omniscient_view = if config.omniscient {
+            Some(VisibilityGrid::new(world.spatial_table.grid_size()))
+        } else {
+            None
+        };
         let animation_context = AnimationContext::default();
-        let animation_rng = Isaac64Rng::from_rng(&mut rng).unwrap();
         let mut self_ = Self {
             world,
             player_entity,
             visibility_grid,
+            omniscient_view,
             config,
-            rng,
+            seed,
+            seed_history: Vec::new(),
+            saved_levels: Vec::new(),
+            rngs,
             animation_context,
-            animation_rng,
+            turn_count: 0,
+            projectile_in_flight: None,
+            shot_outcome: None,
+            outcome: None,
         };
-        self_.animation_tick();
         self_.update_visibility();
         self_
     }
 
+    // Replace the level with a new one generated from a seed chosen by the current level's rng
     pub fn reset(&mut self) {
-        self.rng = rng_from_seed(self.rng.gen());
+        let seed = Seed::random_phrase(self.rngs.get(RngStream::NextLevel));
+        self.regenerate_with_seed(seed);
+    }
+
+    // Replace the level with the one from the given seed, remembering the current level so it can
+    // be returned to later. Levels which have been left are restored as they were left, and other
+    // levels are generated from the seed.
+    pub fn regenerate_with_seed(&mut self, seed: Seed) {
+        info!(
+            "Regenerating level with seed {} (previous seed {})",
+            seed, self.seed
+        );
+        self.cancel_projectile();
         let world_size = self.world.spatial_table.grid_size();
-        let Terrain {
-            world,
-            player_entity,
-        } = Terrain::generate(world_size, &mut self.rng);
-        let visibility_grid = VisibilityGrid::new(world_size);
-        self.world = world;
-        self.player_entity = player_entity;
-        self.visibility_grid = visibility_grid;
+        let level = match self
+            .saved_levels
+            .iter()
+            .position(|level| level.seed == seed)
+        {
+            Some(index) => {
+                info!("Returning to saved level with seed {}", seed);
+                self.saved_levels.remove(index)
+            }
+            None => {
+                let mut rngs = RngStreams::new(seed.value());
+                let Terrain {
+                    world,
+                    player_entity,
+                } = make_terrain(world_size, &self.config, &mut rngs);
+                SavedLevel {
+                    seed,
+                    world,
+                    player_entity,
+                    visibility_grid: VisibilityGrid::new(world_size),
+                    rngs,
+                    turn_count: 0,
+                }
+            }
+        };
+        let previous_level = self.swap_level(level);
+        let seed = &self.seed;
+        self.seed_history
+            .retain(|previous_seed| previous_seed != seed && *previous_seed != previous_level.seed);
+        self.seed_history.push(previous_level.seed.clone());
+        if self.seed_history.len() > MAX_SEED_HISTORY {
+            self.seed_history.remove(0);
+        }
+        self.saved_levels.push(previous_level);
+        let seed_history = &self.seed_history;
+        self.saved_levels
+            .retain(|level| seed_history.contains(&level.seed));
+        if self.omniscient_view.is_some() {
+            self.omniscient_view = Some(VisibilityGrid::new(world_size));
+        }
+        self.outcome = None;
+        self.animation_context = AnimationContext::default();
         self.update_visibility();
     }
 
-    fn update_visibility(&mut self) {
-        let update_fn = |data: &mut VisibleCellData, coord| data.update(&self.world, coord);
+    // Make the given level the current level, returning the level it replaced
+    fn swap_level(&mut self, level: SavedLevel) -> SavedLevel {
+        SavedLevel {
+            seed: mem::replace(&mut self.seed, level.seed),
+            world: mem::replace(&mut self.world, level.world),
+            player_entity: mem::replace(&mut self.player_entity, level.player_entity),
+            visibility_grid: mem::replace(&mut self.visibility_grid, level.visibility_grid),
+            rngs: mem::replace(&mut self.rngs, level.rngs),
+            turn_count: mem::replace(&mut self.turn_count, level.turn_count),
+        }
+    }
 
-        if self.config.omniscient {
-            self.visibility_grid.update_omniscient_custom(
+    fn update_visibility(&mut self) {
+        let player_coord = self.get_player_coord();
+        // Keep track of what the player has seen even while they are omniscient, so they don't
+        // remember the entire level when omniscience is turned off
+        self.visibility_grid.update_custom(
+            Rgb24::new_grey(0),
+            &self.world,
+            PLAYER_VISION_DISTANCE,
+            player_coord,
+            |data: &mut VisibleCellData, coord| data.update(&self.world, coord, Some(player_coord)),
+        );
+        if let Some(omniscient_view) = self.omniscient_view.as_mut() {
+            omniscient_view.update_omniscient_custom(
                 Rgb24::new_grey(255),
                 &self.world,
-                update_fn,
-            );
-        } else {
-            let player_coord = self.get_player_coord();
-            self.visibility_grid.update_custom(
-                Rgb24::new_grey(0),
-                &self.world,
-                PLAYER_VISION_DISTANCE,
-                player_coord,
-                update_fn,
+                |data: &mut VisibleCellData, coord| data.update(&self.world, coord, None),
             );
         }
     }
 
+    pub fn is_omniscient(&self) -> bool {
+        self.omniscient_view.is_some()
+    }
+
+    pub fn set_omniscient(&mut self, omniscient: bool) {
+        if omniscient == self.is_omniscient() {
+            return;
+        }
+        info!("Setting omniscience to {}", omniscient);
+        self.omniscient_view = if omniscient {
+            Some(VisibilityGrid::new(self.world_size()))
+        } else {
+            None
+        };
+        self.update_visibility();
+    }
+
     fn open_door(&mut self, entity: Entity) {
         self.world.components.apply_entity_update(
             entity,
@@ -389,6 +732,7 @@ impl Game {
                 opacity: None,
             },
         );
+        self.world.terrain_changed();
     }
 
     fn close_door(&mut self, entity: Entity) {
@@ -401,6 +745,7 @@ impl Game {
                 opacity: 255,
             },
         );
+        self.world.terrain_changed();
     }
 
     fn crush_grass(&mut self, entity: Entity) {
@@ -412,6 +757,22 @@ impl Game {
                 opacity: 0,
             },
         );
+        self.world.terrain_changed();
+    }
+
+    // Grass is crushed once a creature leaves it rather than when they enter it, so creatures can
+    // hide in the grass they are standing in
+    fn crush_grass_left_behind(&mut self, coord: Coord) {
+        if let Some(&Layers {
+            feature: Some(feature_entity),
+            ..
+        }) = self.world.spatial_table.layers_at(coord)
+        {
+            if let Some(GrassState::Normal) = self.world.components.grass_state.get(feature_entity)
+            {
+                self.crush_grass(feature_entity);
+            }
+        }
     }
 
     fn open_door_entity_adjacent_to_coord(&self, coord: Coord) -> Option<Entity> {
@@ -432,7 +793,7 @@ impl Game {
     }
 
     // Returns the coordinate of the player character
-    fn get_player_coord(&self) -> Coord {
+    pub fn get_player_coord(&self) -> Coord {
         self.world
             .spatial_table
             .coord_of(self.player_entity)
@@ -440,8 +801,9 @@ impl Game {
     }
 
     // Try to the player character one cell in the given direction. This may fail, or cause an
-    // alternative action to happen, such as opening or closing doors.
-    fn try_move_player(&mut self, direction: CardinalDirection) {
+    // alternative action to happen, such as opening or closing doors. Returns true iff the player
+    // took a turn.
+    fn try_move_player(&mut self, direction: CardinalDirection) -> bool {
         let player_coord = self.get_player_coord();
         let new_player_coord = player_coord + direction.coord();
         if let Some(&Layers {
@@ -451,64 +813,289 @@ impl Game {
         {
             // If the player bumps into a door, open the door
             if let Some(DoorState::Closed) = self.world.components.door_state.get(feature_entity) {
+                debug!("Player opened door at {:?}", new_player_coord);
                 self.open_door(feature_entity);
-                return;
+                self.make_noise(Noise {
+                    source: new_player_coord,
+                    loudness: DOOR_NOISE,
+                });
+                return true;
             }
             // Don't let the player walk through solid entities
             if self.world.components.solid.contains(feature_entity) {
                 if let Some(open_door_entity) =
                     self.open_door_entity_adjacent_to_coord(player_coord)
                 {
+                    debug!("Player closed door next to {:?}", player_coord);
                     self.close_door(open_door_entity);
+                    if let Some(door_coord) = self.world.spatial_table.coord_of(open_door_entity) {
+                        self.make_noise(Noise {
+                            source: door_coord,
+                            loudness: DOOR_NOISE,
+                        });
+                    }
+                    return true;
                 }
-                return;
+                return false;
             }
             if let Some(GrassState::Normal) = self.world.components.grass_state.get(feature_entity)
             {
-                self.crush_grass(feature_entity);
+                debug!("Player pushed into grass at {:?}", new_player_coord);
+                self.make_noise(Noise {
+                    source: new_player_coord,
+                    loudness: GRASS_NOISE,
+                });
             }
         }
-        self.world
+        if let Err(e) = self
+            .world
             .spatial_table
             .update_coord(self.player_entity, new_player_coord)
-            .unwrap();
+        {
+            // Something other than a solid feature is in the way, e.g. another character
+            debug!("Player blocked at {:?}: {:?}", new_player_coord, e);
+            return false;
+        }
+        debug!("Player moved to {:?}", new_player_coord);
+        self.crush_grass_left_behind(player_coord);
+        if self.world.is_water_at(new_player_coord) {
+            self.make_noise(Noise {
+                source: new_player_coord,
+                loudness: WATER_NOISE,
+            });
+        }
+        true
+    }
+
+    // Move the player character directly to the given coordinate, unless something solid is there
+    pub fn teleport_player(&mut self, coord: Coord) -> Result<(), String> {
+        let layers = self
+            .world
+            .spatial_table
+            .layers_at(coord)
+            .ok_or_else(|| format!("{:?} is outside the level", coord))?;
+        if let Some(feature_entity) = layers.feature {
+            if self.world.components.solid.contains(feature_entity) {
+                return Err(format!("{:?} is blocked", coord));
+            }
+        }
+        self.world
+            .spatial_table
+            .update_coord(self.player_entity, coord)
+            .map_err(|e| format!("can't move player to {:?}: {:?}", coord, e))?;
+        info!("Player teleported to {:?}", coord);
+        self.update_visibility();
+        self.update_outcome();
+        Ok(())
+    }
+
+    // Write every entity in the world along with all its components to the log. Returns the
+    // number of entities written.
+    pub fn log_all_entities(&self) -> usize {
+        let mut count = 0;
+        for (coord, layers) in self.world.spatial_table.enumerate() {
+            layers.option_for_each_enumerate(|&entity, layer| {
+                info!(
+                    "{:?} at {:?} in {:?} layer: {:?}",
+                    entity,
+                    coord,
+                    layer,
+                    self.world.components.clone_entity_data(entity)
+                );
+                count += 1;
+            });
+        }
+        count
+    }
+
+    // Describe every entity at the given coordinate, along with all of its components
+    pub fn describe_entities_at(&self, coord: Coord) -> Vec<EntityDescription> {
+        let mut descriptions = Vec::new();
+        if let Some(layers) = self.world.spatial_table.layers_at(coord) {
+            layers.option_for_each_enumerate(|&entity, layer| {
+                descriptions.push(EntityDescription {
+                    entity,
+                    layer,
+                    components: self.world.describe_components(entity),
+                });
+            });
+        }
+        descriptions
     }
 
     // Move the player character one cell in the given direction
     pub fn move_player(&mut self, direction: CardinalDirection) {
-        self.try_move_player(direction);
+        if self.try_move_player(direction) {
+            self.end_player_turn();
+        } else {
+            self.update_visibility();
+        }
+    }
+
+    // Let the rest of the world react to the player's turn, then check whether the game is over
+    fn end_player_turn(&mut self) {
+        self.turn_count += 1;
+        self.monster_turns();
+        self.environment_turn();
         self.update_visibility();
+        self.update_outcome();
     }
 
-    // Returns an iterator over each coordinate of the world, along with the visibility of each
-    // corresponding cell
-    pub fn enumerate_cell_visibility(
-        &self,
-    ) -> impl Iterator<Item = (Coord, CellVisibility<&VisibleCellData>)> {
-        self.visibility_grid.enumerate()
+    // Returns true iff the player can currently see a creature other than themself
+    fn is_monster_in_view(&self) -> bool {
+        self.visibility_grid
+            .enumerate()
+            .any(|(_, visibility)| match visibility {
+                CellVisibility::Current { data, .. } => data
+                    .entity_data
+                    .character
+                    .as_ref()
+                    .is_some_and(|entity_data| !matches!(entity_data.tile, Tile::Player)),
+                _ => false,
+            })
     }
 
-    /// Returns true iff a wall has been seen by the player at the given coord
-    pub fn is_wall_known_at(&self, coord: Coord) -> bool {
-        if let Some(data) = self.visibility_grid.get_data(coord) {
-            data.entity_data
-                .feature
-                .as_ref()
-                .map(|entity_data| entity_data.tile.is_wall())
-                .unwrap_or(false)
-        } else {
-            false
+    // Take a step towards the nearest cell the player has seen which is next to a cell they
+    // haven't seen. Exploring stops when a monster comes into view.
+    pub fn auto_explore(&mut self) -> Result<(), String> {
+        if self.is_monster_in_view() {
+            return Err("there is a monster in view".to_string());
+        }
+        let world_size = self.world_size();
+        let frontier = world_size
+            .coord_iter_row_major()
+            .filter(|&coord| {
+                // Doors count as walls when drawing the level, but can be explored through
+                let is_explorable = self.visibility_grid.get_data(coord).is_some_and(|data| {
+                    data.entity_data.feature.as_ref().is_none_or(|entity_data| {
+                        !matches!(entity_data.tile, Tile::Wall | Tile::CaveWall)
+                    })
+                });
+                is_explorable
+                    && CardinalDirection::all().any(|direction| {
+                        let neighbour_coord = coord + direction.coord();
+                        neighbour_coord.is_valid(world_size)
+                            && self.visibility_grid.get_data(neighbour_coord).is_none()
+                    })
+            })
+            .collect::<Vec<_>>();
+        if frontier.is_empty() {
+            return Err("there is nowhere left to explore".to_string());
+        }
+        let player_coord = self.get_player_coord();
+        let step = self
+            .world
+            .explore_distance_map(frontier)
+            .step_toward(player_coord)
+            .ok_or_else(|| "there is no path to anywhere unexplored".to_string())?;
+        self.move_player(CardinalDirection::from_unit_coord(step - player_coord));
+        Ok(())
+    }
+
+    // Take a step along the cheapest path to a cell the player has seen. Travelling stops when a
+    // monster comes into view.
+    pub fn travel_towards(&mut self, destination: Coord) -> Result<(), String> {
+        if self.is_monster_in_view() {
+            return Err("there is a monster in view".to_string());
+        }
+        if self.visibility_grid.get_data(destination).is_none() {
+            return Err("you haven't seen there".to_string());
+        }
+        if self.world.movement_cost(destination).is_none() {
+            return Err("you can't walk there".to_string());
+        }
+        let player_coord = self.get_player_coord();
+        if player_coord == destination {
+            return Err("you are already there".to_string());
         }
+        let step = self
+            .world
+            .distance_map_to(destination)
+            .step_toward(player_coord)
+            .ok_or_else(|| "there is no path there".to_string())?;
+        self.move_player(CardinalDirection::from_unit_coord(step - player_coord));
+        Ok(())
+    }
+
+    pub fn player_health(&self) -> Health {
+        *self
+            .world
+            .components
+            .health
+            .get(self.player_entity)
+            .expect("player does not have health")
+    }
+
+    // Returns true iff the player has seen every cell of the level which isn't a wall
+    fn is_level_explored(&self) -> bool {
+        self.world
+            .spatial_table
+            .enumerate()
+            .filter(|(_, layers)| layers.floor.is_some())
+            .all(|(coord, _)| self.visibility_grid.get_data(coord).is_some())
+    }
+
+    // Checking whether the level is explored visits every cell, so this is only done once the
+    // player has done something which might end the game
+    fn update_outcome(&mut self) {
+        self.outcome = if self.player_health().current == 0 {
+            Some(GameOutcome::Defeat)
+        } else if self.is_level_explored() {
+            Some(GameOutcome::Victory)
+        } else {
+            None
+        };
+    }
+
+    // Returns the outcome of the game, or `None` if the game is still in progress
+    pub fn outcome(&self) -> Option<GameOutcome> {
+        self.outcome
+    }
+
+    // Returns true iff the player is hidden in grass, so monsters can only spot them up close
+    pub fn is_player_concealed(&self) -> bool {
+        self.world.is_concealed_at(self.get_player_coord())
+    }
+
+    pub fn turn_count(&self) -> u64 {
+        self.turn_count
+    }
+
+    // What the player can see, which is the entire level while they are omniscient
+    pub fn visibility_grid(&self) -> &VisibilityGrid<VisibleCellData> {
+        self.omniscient_view
+            .as_ref()
+            .unwrap_or(&self.visibility_grid)
+    }
+
+    // Returns a visibility grid in which every cell of the world is visible, regardless of what
+    // the player has seen
+    pub fn omniscient_visibility_grid(&self) -> VisibilityGrid<VisibleCellData> {
+        omniscient_visibility_grid(&self.world)
+    }
+
+    pub fn world_size(&self) -> Size {
+        self.world.spatial_table.grid_size()
+    }
+
+    pub fn seed(&self) -> &Seed {
+        &self.seed
+    }
+
+    // Seeds of previously generated levels, most recent last
+    pub fn seed_history(&self) -> &[Seed] {
+        &self.seed_history
     }
 
     pub fn animation_tick(&mut self) {
         self.animation_context.tick(
             RealtimeContext {
                 world: &mut self.world,
-                rng: &mut self.animation_rng,
+                rng: self.rngs.get(RngStream::Animation),
             },
             FRAME_DURATION,
         );
+        self.resolve_landed_projectile();
         self.update_visibility();
     }
 }
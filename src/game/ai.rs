@@ -0,0 +1,248 @@
+use super::{stealth::Noise, DoorState, Game, Health, Layers};
+use crate::rng::RngStream;
+use gridbugs::{coord_2d::Coord, direction::CardinalDirection, entity_table::Entity};
+use log::debug;
+use rand::seq::SliceRandom;
+
+const MONSTER_ATTACK_DAMAGE: u32 = 1;
+
+// How long a monster searches after losing track of the player or finding nothing at the source
+// of a noise
+const SEARCH_TURNS: u32 = 10;
+
+// Searching monsters stay this close to where they started searching
+const SEARCH_DISTANCE_SQUARED: u32 = 3 * 3;
+
+// Monsters flee from the player once their health drops to this fraction of their maximum
+const FLEE_HEALTH_DIVISOR: u32 = 3;
+
+// Fleeing monsters stop once they are this far (in movement cost) from where they saw the player
+const FLEE_DISTANCE: u32 = 12;
+
+// What a monster is currently doing
+#[derive(Clone, Copy, Debug)]
+pub enum Ai {
+    // Waiting until it sees or hears something
+    Idle,
+    // Heading to where it heard a noise
+    Investigating(Coord),
+    // Chasing the player, who was last seen at the given coord
+    Hunting(Coord),
+    // Wandering around near where the player was last seen or heard
+    Searching { around: Coord, turns_left: u32 },
+    // Running away from where the player was last seen
+    Fleeing(Coord),
+}
+
+pub(super) fn is_badly_wounded(health: &Health) -> bool {
+    health.current * FLEE_HEALTH_DIVISOR <= health.max
+}
+
+impl Game {
+    // Send every monster which can hear the noise to investigate it. Monsters already chasing or
+    // fleeing from the player ignore noise.
+    pub(super) fn make_noise(&mut self, noise: Noise) {
+        debug!("Noise at {:?} (loudness {})", noise.source, noise.loudness);
+        for coord in self.world.cells_reached_by_noise(noise) {
+            let character_entity = self
+                .world
+                .spatial_table
+                .layers_at(coord)
+                .and_then(|layers| layers.character);
+            if let Some(character_entity) = character_entity {
+                if let Some(ai) = self.world.components.ai.get_mut(character_entity) {
+                    if !matches!(ai, Ai::Hunting(_) | Ai::Fleeing(_)) {
+                        debug!("{:?} heard a noise at {:?}", character_entity, noise.source);
+                        *ai = Ai::Investigating(noise.source);
+                    }
+                }
+            }
+        }
+    }
+
+    // Let every monster take a turn, in a consistent order so that replaying a seed gives the same
+    // game
+    pub(super) fn monster_turns(&mut self) {
+        let mut monster_entities = self.world.components.ai.entities().collect::<Vec<_>>();
+        monster_entities.sort();
+        for monster_entity in monster_entities {
+            if self.player_health().current == 0 {
+                break;
+            }
+            self.monster_turn(monster_entity);
+        }
+    }
+
+    fn monster_turn(&mut self, monster_entity: Entity) {
+        let (Some(monster_coord), Some(&ai)) = (
+            self.world.spatial_table.coord_of(monster_entity),
+            self.world.components.ai.get(monster_entity),
+        ) else {
+            return;
+        };
+        let player_coord = self.get_player_coord();
+        let ai = if self.monster_spots_player(monster_entity, monster_coord, player_coord) {
+            let is_badly_wounded = self
+                .world
+                .components
+                .health
+                .get(monster_entity)
+                .is_some_and(is_badly_wounded);
+            if is_badly_wounded {
+                Ai::Fleeing(player_coord)
+            } else {
+                Ai::Hunting(player_coord)
+            }
+        } else {
+            ai
+        };
+        let ai = match ai {
+            Ai::Idle => Ai::Idle,
+            Ai::Fleeing(from) => {
+                if self.monster_step_away(monster_entity, monster_coord, from) {
+                    ai
+                } else {
+                    debug!("{:?} stopped fleeing", monster_entity);
+                    Ai::Idle
+                }
+            }
+            Ai::Searching { turns_left: 0, .. } => {
+                debug!("{:?} gave up searching", monster_entity);
+                Ai::Idle
+            }
+            Ai::Searching { around, turns_left } => {
+                self.monster_search_step(monster_entity, monster_coord, around);
+                Ai::Searching {
+                    around,
+                    turns_left: turns_left - 1,
+                }
+            }
+            Ai::Investigating(target) | Ai::Hunting(target) if target == monster_coord => {
+                debug!("{:?} found nothing at {:?}", monster_entity, target);
+                Ai::Searching {
+                    around: target,
+                    turns_left: SEARCH_TURNS,
+                }
+            }
+            Ai::Hunting(target)
+                if target == player_coord && (target - monster_coord).magnitude2() == 1 =>
+            {
+                self.monster_attack(monster_entity);
+                ai
+            }
+            Ai::Investigating(target) | Ai::Hunting(target) => {
+                if self.monster_step_towards(monster_entity, monster_coord, target) {
+                    ai
+                } else {
+                    debug!("{:?} can't reach {:?}", monster_entity, target);
+                    Ai::Idle
+                }
+            }
+        };
+        self.world.components.ai.insert(monster_entity, ai);
+    }
+
+    // Update what a monster can see, and return true iff it notices the player
+    fn monster_spots_player(
+        &mut self,
+        monster_entity: Entity,
+        monster_coord: Coord,
+        player_coord: Coord,
+    ) -> bool {
+        // Taken out of the world while it's updated, since updating it needs to look at the world
+        let mut perception = match self.world.components.perception.remove(monster_entity) {
+            Some(perception) => perception,
+            None => return false,
+        };
+        perception.update(&self.world, monster_coord);
+        let spotted = perception.can_spot(&self.world, monster_coord, player_coord);
+        self.world
+            .components
+            .perception
+            .insert(monster_entity, perception);
+        spotted
+    }
+
+    // Move a searching monster to a random nearby cell
+    fn monster_search_step(&mut self, monster_entity: Entity, from: Coord, around: Coord) {
+        let candidates = CardinalDirection::all()
+            .map(|direction| from + direction.coord())
+            .filter(|&coord| {
+                (coord - around).magnitude2() <= SEARCH_DISTANCE_SQUARED
+                    && self.world.movement_cost(coord).is_some()
+            })
+            .collect::<Vec<_>>();
+        if let Some(&step) = candidates.choose(self.rngs.get(RngStream::Ai)) {
+            self.monster_move(monster_entity, step);
+        }
+    }
+
+    fn monster_attack(&mut self, monster_entity: Entity) {
+        if let Some(health) = self.world.components.health.get_mut(self.player_entity) {
+            health.current = health.current.saturating_sub(MONSTER_ATTACK_DAMAGE);
+            debug!(
+                "{:?} attacked the player ({}/{} health remaining)",
+                monster_entity, health.current, health.max
+            );
+        }
+    }
+
+    // Move a monster one step towards a target. Returns false iff there is no path to the target.
+    fn monster_step_towards(&mut self, monster_entity: Entity, from: Coord, to: Coord) -> bool {
+        match self.world.distance_map_to(to).step_toward(from) {
+            Some(step) => {
+                self.monster_move(monster_entity, step);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Move a monster one step further from a coord. Returns false iff the monster is far enough
+    // away already or is cornered.
+    fn monster_step_away(&mut self, monster_entity: Entity, from: Coord, away_from: Coord) -> bool {
+        let distance_map = self.world.distance_map_to(away_from);
+        if distance_map
+            .distance(from)
+            .is_none_or(|distance| distance >= FLEE_DISTANCE)
+        {
+            return false;
+        }
+        match distance_map.step_away(from) {
+            Some(step) => {
+                self.monster_move(monster_entity, step);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Move a monster to an adjacent cell, opening a door in its way and crushing the grass it
+    // leaves. Monsters don't listen out for each other, so the noise they make isn't tracked.
+    fn monster_move(&mut self, monster_entity: Entity, step: Coord) {
+        if let Some(&Layers {
+            feature: Some(feature_entity),
+            ..
+        }) = self.world.spatial_table.layers_at(step)
+        {
+            if let Some(DoorState::Closed) = self.world.components.door_state.get(feature_entity) {
+                debug!("{:?} opened door at {:?}", monster_entity, step);
+                self.open_door(feature_entity);
+                return;
+            }
+        }
+        let Some(from) = self.world.spatial_table.coord_of(monster_entity) else {
+            return;
+        };
+        // Another character may be in the way, in which case the monster waits for it to move
+        if self
+            .world
+            .spatial_table
+            .update_coord(monster_entity, step)
+            .is_ok()
+        {
+            debug!("{:?} moved to {:?}", monster_entity, step);
+            self.crush_grass_left_behind(from);
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use super::{DoorState, GrassState, Layers, World};
+use gridbugs::{
+    coord_2d::{Coord, Size},
+    direction::CardinalDirection,
+    grid_2d::Grid,
+};
+use log::debug;
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+// Extra cost of moving through cells which slow down movement or make noise
+const CLOSED_DOOR_EXTRA_COST: u32 = 2;
+const WATER_EXTRA_COST: u32 = 2;
+const GRASS_EXTRA_COST: u32 = 1;
+
+// The cache of maps to single coords is cleared when it grows beyond this many maps
+const MAX_CACHED_DISTANCE_MAPS: usize = 16;
+
+// The cost of travelling from every cell of the level to the nearest of a set of goals. Paths
+// toward a goal are found by repeatedly stepping to the neighbouring cell with the lowest cost.
+pub struct DistanceMap {
+    distances: Grid<Option<u32>>,
+    // The cost of entering each cell, or `None` if it can't be entered
+    costs: Grid<Option<u32>>,
+}
+
+impl DistanceMap {
+    // Compute the distance from each cell to the nearest goal, where `cost` returns the cost of
+    // entering a cell or `None` if it can't be entered. Cells further than `max_distance` from
+    // every goal are treated as unreachable.
+    pub fn compute<F: Fn(Coord) -> Option<u32>>(
+        size: Size,
+        goals: &[Coord],
+        cost: F,
+        max_distance: u32,
+    ) -> Self {
+        let costs = Grid::new_fn(size, cost);
+        let mut distances = Grid::new_copy(size, None);
+        let mut queue = BinaryHeap::new();
+        for &goal in goals {
+            if let Some(distance) = distances.get_mut(goal) {
+                *distance = Some(0);
+                queue.push(Reverse((0, goal.x, goal.y)));
+            }
+        }
+        while let Some(Reverse((distance, x, y))) = queue.pop() {
+            let coord = Coord::new(x, y);
+            if *distances.get_checked(coord) != Some(distance) {
+                continue;
+            }
+            // Moving from a neighbour to this cell costs the cost of entering this cell. Only goals
+            // can be impassable, and they are treated as costing 1 so that they can be reached.
+            let cost_to_enter = costs.get_checked(coord).unwrap_or(1);
+            let neighbour_distance = distance + cost_to_enter;
+            if neighbour_distance > max_distance {
+                continue;
+            }
+            for direction in CardinalDirection::all() {
+                let neighbour_coord = coord + direction.coord();
+                if costs.get(neighbour_coord).copied().flatten().is_none() {
+                    continue;
+                }
+                if let Some(existing) = distances.get_mut(neighbour_coord) {
+                    if existing.is_none_or(|existing| neighbour_distance < existing) {
+                        *existing = Some(neighbour_distance);
+                        queue.push(Reverse((
+                            neighbour_distance,
+                            neighbour_coord.x,
+                            neighbour_coord.y,
+                        )));
+                    }
+                }
+            }
+        }
+        Self { distances, costs }
+    }
+
+    // The cost of travelling from the given coord to the nearest goal, or `None` if no goal can
+    // be reached
+    pub fn distance(&self, coord: Coord) -> Option<u32> {
+        self.distances.get(coord).cloned().flatten()
+    }
+
+    // The coords adjacent to the given coord which can be reached, with their distances
+    fn neighbours(&self, coord: Coord) -> impl '_ + Iterator<Item = (Coord, u32)> {
+        CardinalDirection::all().filter_map(move |direction| {
+            let neighbour_coord = coord + direction.coord();
+            self.distance(neighbour_coord)
+                .map(|distance| (neighbour_coord, distance))
+        })
+    }
+
+    // The adjacent cell to move to in order to follow the cheapest path to the nearest goal, or
+    // `None` if there is no path to a goal or the coord is a goal. Entering the adjacent cell costs
+    // something too, so the nearest neighbour isn't necessarily on the cheapest path.
+    pub fn step_toward(&self, coord: Coord) -> Option<Coord> {
+        let current = self.distance(coord)?;
+        self.neighbours(coord)
+            .filter(|&(_, distance)| distance < current)
+            .min_by_key(|&(neighbour_coord, distance)| {
+                // Goals which can't be entered are treated as costing 1, as in `compute`
+                let cost_to_enter = self.costs.get_checked(neighbour_coord).unwrap_or(1);
+                distance + cost_to_enter
+            })
+            .map(|(coord, _)| coord)
+    }
+
+    // The adjacent cell to move to in order to get further from every goal, or `None` if no
+    // adjacent cell is further away
+    pub fn step_away(&self, coord: Coord) -> Option<Coord> {
+        let current = self.distance(coord)?;
+        self.neighbours(coord)
+            .filter(|&(_, distance)| distance > current)
+            .max_by_key(|&(_, distance)| distance)
+            .map(|(coord, _)| coord)
+    }
+}
+
+// Distance maps which have been computed since the terrain last changed. Only maps whose goals
+// stay the same for a while are cached.
+#[derive(Default)]
+pub struct DistanceMapCache {
+    // Maps to single coords, such as where monsters last saw the player. Monsters chasing the same
+    // target share a map, which is kept until the target moves or the terrain changes.
+    to_coord: HashMap<Coord, DistanceMap>,
+    // The map to the edge of the explored part of the level, which is kept while exploring
+    // through cells which have already been seen
+    explore: Option<(Vec<Coord>, DistanceMap)>,
+}
+
+impl DistanceMapCache {
+    fn len(&self) -> usize {
+        self.to_coord.len() + self.explore.iter().count()
+    }
+}
+
+impl World {
+    // The cost of a creature walking into a cell, or `None` if the cell can't be walked into.
+    // Other creatures are ignored since they will probably have moved by the time the cell is
+    // reached.
+    pub fn movement_cost(&self, coord: Coord) -> Option<u32> {
+        let layers = self.spatial_table.layers_at(coord)?;
+        let mut cost = 1;
+        if let &Layers {
+            feature: Some(feature_entity),
+            ..
+        } = layers
+        {
+            match self.components.door_state.get(feature_entity) {
+                Some(DoorState::Closed) => cost += CLOSED_DOOR_EXTRA_COST,
+                Some(DoorState::Open) => (),
+                None if self.components.solid.contains(feature_entity) => return None,
+                None => (),
+            }
+            if let Some(GrassState::Normal) = self.components.grass_state.get(feature_entity) {
+                cost += GRASS_EXTRA_COST;
+            }
+        }
+        if self.is_water_at(coord) {
+            cost += WATER_EXTRA_COST;
+        }
+        Some(cost)
+    }
+
+    fn compute_distance_map(&self, goals: &[Coord]) -> DistanceMap {
+        DistanceMap::compute(
+            self.spatial_table.grid_size(),
+            goals,
+            |coord| self.movement_cost(coord),
+            u32::MAX,
+        )
+    }
+
+    // Returns a map of the cost of walking from each cell to the given coord. Maps are cached
+    // until the terrain next changes.
+    pub fn distance_map_to(&mut self, goal: Coord) -> &DistanceMap {
+        if !self.distance_map_cache.to_coord.contains_key(&goal) {
+            let map = self.compute_distance_map(&[goal]);
+            let cache = &mut self.distance_map_cache.to_coord;
+            if cache.len() >= MAX_CACHED_DISTANCE_MAPS {
+                cache.clear();
+            }
+            cache.insert(goal, map);
+        }
+        &self.distance_map_cache.to_coord[&goal]
+    }
+
+    // Returns a map of the cost of walking from each cell to the nearest cell of the frontier of
+    // the explored part of the level. The map is cached until the frontier or the terrain
+    // changes.
+    pub fn explore_distance_map(&mut self, frontier: Vec<Coord>) -> &DistanceMap {
+        let is_cached = self
+            .distance_map_cache
+            .explore
+            .as_ref()
+            .is_some_and(|(cached_frontier, _)| *cached_frontier == frontier);
+        if !is_cached {
+            let map = self.compute_distance_map(&frontier);
+            self.distance_map_cache.explore = Some((frontier, map));
+        }
+        let (_, map) = self.distance_map_cache.explore.as_ref().unwrap();
+        map
+    }
+
+    // Must be called whenever something changes which affects the cost of moving through the
+    // level, such as a door opening or grass being crushed
+    pub fn terrain_changed(&mut self) {
+        let cache = &mut self.distance_map_cache;
+        if cache.len() > 0 {
+            debug!("Terrain changed, discarding {} distance maps", cache.len());
+            cache.to_coord.clear();
+            cache.explore = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,106 @@
+use super::{DistanceMap, CLOSED_DOOR_EXTRA_COST, WATER_EXTRA_COST};
+use crate::game::World;
+use gridbugs::coord_2d::{Coord, Size};
+use rand::SeedableRng;
+use rand_isaac::Isaac64Rng;
+
+// Makes a distance map from a grid of costs, where '#' can't be entered and digits are the cost
+// of entering a cell
+fn distance_map(rows: &[&str], goals: &[Coord]) -> DistanceMap {
+    let size = Size::new(rows[0].len() as u32, rows.len() as u32);
+    let cost = |coord: Coord| {
+        let row = rows.get(usize::try_from(coord.y).ok()?)?;
+        let cell = row.chars().nth(usize::try_from(coord.x).ok()?)?;
+        cell.to_digit(10)
+    };
+    DistanceMap::compute(size, goals, cost, u32::MAX)
+}
+
+// Makes a world with a single row of floor, with the given template spawned in the middle
+fn corridor(template: &str) -> World {
+    let size = Size::new(5, 1);
+    let mut rng = Isaac64Rng::seed_from_u64(0);
+    let mut world = World::new(size);
+    for coord in size.coord_iter_row_major() {
+        let floor = if coord.x == 2 && template == "water" {
+            "water"
+        } else {
+            "floor"
+        };
+        world.spawn_from_template(floor, coord, &mut rng).unwrap();
+    }
+    if template != "water" && template != "floor" {
+        world
+            .spawn_from_template(template, Coord::new(2, 0), &mut rng)
+            .unwrap();
+    }
+    world
+}
+
+#[test]
+fn distances_are_the_cost_of_entering_each_cell_on_the_way() {
+    let map = distance_map(&["1121"], &[Coord::new(0, 0)]);
+    let distances = (0..4)
+        .map(|x| map.distance(Coord::new(x, 0)))
+        .collect::<Vec<_>>();
+    assert_eq!(distances, vec![Some(0), Some(1), Some(2), Some(4)]);
+}
+
+#[test]
+fn cells_cut_off_from_every_goal_are_unreachable() {
+    let map = distance_map(&["11#1"], &[Coord::new(0, 0)]);
+    assert_eq!(map.distance(Coord::new(3, 0)), None);
+    assert_eq!(map.step_toward(Coord::new(3, 0)), None);
+    assert_eq!(map.distance(Coord::new(2, 0)), None);
+}
+
+#[test]
+fn step_toward_goes_around_expensive_cells() {
+    let map = distance_map(&["111", "191", "111"], &[Coord::new(2, 1)]);
+    let step = map.step_toward(Coord::new(0, 1)).unwrap();
+    assert!(
+        step == Coord::new(0, 0) || step == Coord::new(0, 2),
+        "{:?}",
+        step
+    );
+    assert_eq!(map.step_toward(Coord::new(2, 1)), None);
+}
+
+#[test]
+fn goals_which_cant_be_entered_can_still_be_reached() {
+    let map = distance_map(&["11#"], &[Coord::new(2, 0)]);
+    assert_eq!(map.distance(Coord::new(1, 0)), Some(1));
+    assert_eq!(map.distance(Coord::new(0, 0)), Some(2));
+    assert_eq!(map.step_toward(Coord::new(0, 0)), Some(Coord::new(1, 0)));
+    assert_eq!(map.step_toward(Coord::new(1, 0)), Some(Coord::new(2, 0)));
+}
+
+#[test]
+fn step_away_goes_further_from_every_goal() {
+    let map = distance_map(&["1111"], &[Coord::new(0, 0)]);
+    assert_eq!(map.step_away(Coord::new(1, 0)), Some(Coord::new(2, 0)));
+    // Cornered at the end of the row
+    assert_eq!(map.step_away(Coord::new(3, 0)), None);
+}
+
+#[test]
+fn cells_beyond_the_max_distance_are_unreachable() {
+    let size = Size::new(5, 1);
+    let map = DistanceMap::compute(size, &[Coord::new(0, 0)], |_| Some(1), 2);
+    assert_eq!(map.distance(Coord::new(2, 0)), Some(2));
+    assert_eq!(map.distance(Coord::new(3, 0)), None);
+}
+
+#[test]
+fn closed_doors_and_water_cost_more_to_walk_through() {
+    let far_end = Coord::new(4, 0);
+    let distance = |mut world: World| world.distance_map_to(Coord::new(0, 0)).distance(far_end);
+    let floor = distance(corridor("floor")).unwrap();
+    assert_eq!(floor, 4);
+    assert_eq!(
+        distance(corridor("door")),
+        Some(floor + CLOSED_DOOR_EXTRA_COST)
+    );
+    assert_eq!(distance(corridor("water")), Some(floor + WATER_EXTRA_COST));
+    assert_eq!(distance(corridor("wall")), None);
+}
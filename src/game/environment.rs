@@ -0,0 +1,205 @@
+use super::{Game, GrassState, Tile, World};
+use crate::rng::RngStream;
+use gridbugs::{coord_2d::Coord, direction::CardinalDirection, entity_table::Entity};
+use log::{debug, warn};
+use rand::Rng;
+
+// The environment changes once every this many turns
+const ENVIRONMENT_TURN_INTERVAL: u64 = 10;
+
+// Chance that each patch of crushed grass grows back each time the environment changes
+const GRASS_REGROWTH_PERCENT: u32 = 5;
+
+// Chance that grass spreads into each cave floor cell next to it which is near water
+const GRASS_SPREAD_PERCENT: u32 = 2;
+
+// Dry floor this close to water may be flooded when the water level rises
+const SHORE_DISTANCE: i32 = 2;
+
+// The number of turns for the water level to rise and fall again
+const SEASON_LENGTH_TURNS: u64 = 500;
+
+// The highest elevation a shore cell can have. Every shore cell is flooded at the height of the
+// wet season.
+const MAX_ELEVATION: u32 = 100;
+
+// A floor cell near water which is flooded while the water level is at or above its elevation
+#[derive(Clone, Copy, Debug)]
+struct ShoreCell {
+    coord: Coord,
+    floor_entity: Entity,
+    elevation: u32,
+    // The template to restore when the water recedes
+    dry_template: &'static str,
+}
+
+// The state of the slow environmental changes to a level
+#[derive(Clone, Debug, Default)]
+pub struct Environment {
+    shore_cells: Vec<ShoreCell>,
+}
+
+// How far the water has risen over the shore at a point in time, between 0 (the level as
+// generated) and `MAX_ELEVATION` (every shore cell flooded)
+fn water_level(turn_count: u64) -> u32 {
+    let half_season = SEASON_LENGTH_TURNS / 2;
+    let turn_in_season = turn_count % SEASON_LENGTH_TURNS;
+    let turns_from_dry_season = if turn_in_season < half_season {
+        turn_in_season
+    } else {
+        SEASON_LENGTH_TURNS - turn_in_season
+    };
+    (turns_from_dry_season * MAX_ELEVATION as u64 / half_season) as u32
+}
+
+impl World {
+    fn floor_tile_at(&self, coord: Coord) -> Option<(Entity, Tile)> {
+        let floor_entity = self.spatial_table.layers_at(coord)?.floor?;
+        let &tile = self.components.tile.get(floor_entity)?;
+        Some((floor_entity, tile))
+    }
+
+    fn is_near_water(&self, coord: Coord) -> bool {
+        for y in -SHORE_DISTANCE..=SHORE_DISTANCE {
+            for x in -SHORE_DISTANCE..=SHORE_DISTANCE {
+                let offset = Coord::new(x, y);
+                if offset.magnitude2() <= (SHORE_DISTANCE * SHORE_DISTANCE) as u32
+                    && self.is_water_at(coord + offset)
+                {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    // Find the floor cells which may be flooded as the seasons change. Cells closer to the water
+    // tend to have lower elevations, so the water spreads outwards as it rises.
+    pub fn init_environment<R: Rng>(&mut self, rng: &mut R) {
+        let mut shore_cells = Vec::new();
+        for coord in self.spatial_table.grid_size().coord_iter_row_major() {
+            let (floor_entity, dry_template) = match self.floor_tile_at(coord) {
+                Some((floor_entity, Tile::Floor)) => (floor_entity, "floor"),
+                Some((floor_entity, Tile::CaveFloor)) => (floor_entity, "cave_floor"),
+                _ => continue,
+            };
+            if !self.is_near_water(coord) {
+                continue;
+            }
+            let is_next_to_water = CardinalDirection::all()
+                .any(|direction| self.is_water_at(coord + direction.coord()));
+            let min_elevation = if is_next_to_water {
+                1
+            } else {
+                MAX_ELEVATION / 2
+            };
+            shore_cells.push(ShoreCell {
+                coord,
+                floor_entity,
+                elevation: rng.gen_range(min_elevation..=MAX_ELEVATION),
+                dry_template,
+            });
+        }
+        debug!("Level has {} shore cells", shore_cells.len());
+        self.environment = Environment { shore_cells };
+    }
+
+    // Replace all the components of an existing entity with those of a template
+    fn change_template<R: Rng>(&mut self, entity: Entity, name: &str, rng: &mut R) {
+        let template = match self.templates.get(name) {
+            Some(template) => template,
+            None => {
+                warn!("No entity template named {}", name);
+                return;
+            }
+        };
+        self.components
+            .update_entity_data(entity, template.entity_data.clone());
+        self.realtime_components.remove_entity(entity);
+        self.insert_animation(entity, template.animation, rng);
+        self.terrain_changed();
+    }
+}
+
+impl Game {
+    // Advance the slow changes to the level: grass regrowing and spreading, and water rising and
+    // falling with the seasons. Randomness comes from a dedicated rng stream so that the changes
+    // only depend on the level's seed and the number of turns taken.
+    pub(super) fn environment_turn(&mut self) {
+        if !self.turn_count.is_multiple_of(ENVIRONMENT_TURN_INTERVAL) {
+            return;
+        }
+        self.grow_grass();
+        self.update_water_level();
+    }
+
+    fn grow_grass(&mut self) {
+        let rng = self.rngs.get(RngStream::Environment);
+        let world = &mut self.world;
+        let mut regrow = Vec::new();
+        let mut spread = Vec::new();
+        // Cells are visited in a fixed order so the same rng values affect the same cells
+        for (coord, layers) in world.spatial_table.enumerate() {
+            match layers.feature {
+                Some(feature_entity) => {
+                    if let Some(GrassState::Crushed) =
+                        world.components.grass_state.get(feature_entity)
+                    {
+                        if rng.gen_range(0..100) < GRASS_REGROWTH_PERCENT {
+                            regrow.push(feature_entity);
+                        }
+                    }
+                }
+                None => {
+                    if let Some((_, Tile::CaveFloor)) = world.floor_tile_at(coord) {
+                        let is_next_to_grass = CardinalDirection::all().any(|direction| {
+                            world
+                                .spatial_table
+                                .layers_at(coord + direction.coord())
+                                .and_then(|layers| layers.feature)
+                                .is_some_and(|entity| world.components.grass_state.contains(entity))
+                        });
+                        if is_next_to_grass
+                            && world.is_near_water(coord)
+                            && rng.gen_range(0..100) < GRASS_SPREAD_PERCENT
+                        {
+                            spread.push(coord);
+                        }
+                    }
+                }
+            }
+        }
+        for entity in regrow {
+            world.change_template(entity, "grass", rng);
+        }
+        for coord in spread {
+            match world.spawn_from_template("grass", coord, rng) {
+                Ok(_) => world.terrain_changed(),
+                Err(e) => warn!("Failed to spread grass to {:?}: {}", coord, e),
+            }
+        }
+    }
+
+    // Flood or drain shore cells to match the current water level
+    fn update_water_level(&mut self) {
+        let water_level = water_level(self.turn_count);
+        let rng = self.rngs.get(RngStream::Environment);
+        let world = &mut self.world;
+        for i in 0..world.environment.shore_cells.len() {
+            let shore_cell = world.environment.shore_cells[i];
+            let should_flood = shore_cell.elevation <= water_level;
+            if should_flood == world.is_water_at(shore_cell.coord) {
+                continue;
+            }
+            let template = if should_flood {
+                "water"
+            } else {
+                shore_cell.dry_template
+            };
+            world.change_template(shore_cell.floor_entity, template, rng);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test;
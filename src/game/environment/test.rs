@@ -0,0 +1,67 @@
+use super::{water_level, MAX_ELEVATION, SEASON_LENGTH_TURNS};
+use crate::{
+    game::{Config, Game, World},
+    seed::Seed,
+};
+use gridbugs::coord_2d::Size;
+
+#[test]
+fn water_rises_and_falls_with_the_seasons() {
+    let half_season = SEASON_LENGTH_TURNS / 2;
+    assert_eq!(water_level(0), 0);
+    assert_eq!(water_level(half_season), MAX_ELEVATION);
+    assert_eq!(water_level(SEASON_LENGTH_TURNS), 0);
+    assert_eq!(water_level(half_season / 2), MAX_ELEVATION / 2);
+    for turn in 0..half_season {
+        assert!(water_level(turn) <= water_level(turn + 1), "{}", turn);
+        assert_eq!(
+            water_level(turn),
+            water_level(SEASON_LENGTH_TURNS - turn),
+            "{}",
+            turn
+        );
+    }
+    for turn in 0..SEASON_LENGTH_TURNS {
+        assert!(water_level(turn) <= MAX_ELEVATION);
+        assert_eq!(
+            water_level(turn),
+            water_level(turn + 3 * SEASON_LENGTH_TURNS)
+        );
+    }
+}
+
+fn flooded_shore_cells(world: &World) -> usize {
+    world
+        .environment
+        .shore_cells
+        .iter()
+        .filter(|shore_cell| world.is_water_at(shore_cell.coord))
+        .count()
+}
+
+#[test]
+fn returning_to_a_level_keeps_its_environment() {
+    let seed = Seed::Number(1);
+    let config = Config {
+        omniscient: false,
+        rng_seed: Some(seed.clone()),
+        generator: Default::default(),
+        generation_params: Default::default(),
+        map: None,
+    };
+    let mut game = Game::new(Size::new(40, 30), config);
+    for _ in 0..SEASON_LENGTH_TURNS / 4 {
+        game.turn_count += 1;
+        game.environment_turn();
+    }
+    let turn_count = game.turn_count;
+    let flooded = flooded_shore_cells(&game.world);
+    // The test is only meaningful if some of the shore has flooded
+    assert!(flooded > 0);
+    game.regenerate_with_seed(Seed::Number(2));
+    assert_eq!(game.turn_count, 0);
+    game.regenerate_with_seed(seed);
+    assert_eq!(game.turn_count, turn_count);
+    assert_eq!(flooded_shore_cells(&game.world), flooded);
+    assert_eq!(game.seed_history(), &[Seed::Number(2)]);
+}
@@ -0,0 +1,110 @@
+use super::{stealth::CONCEALED_SPOT_DISTANCE_SQUARED, Layer, World};
+use gridbugs::{
+    coord_2d::{Coord, Size},
+    rgb_int::Rgb24,
+    visible_area_detection::{
+        vision_distance, CellVisibility, Light, VisibilityGrid, World as VisibleWorld,
+    },
+};
+use std::fmt;
+
+// Creatures next to each other (including diagonally) always notice each other, even in the dark
+const MIN_SPOT_DISTANCE_SQUARED: u32 = 2;
+
+// The world as lit for a creature looking for others. Lights carried by other creatures are left
+// out, since a creature's own light is brightest where it stands and would always give it away.
+// The viewer's own light still shows it where others are.
+struct LitForViewer<'a> {
+    world: &'a World,
+    eye: Coord,
+}
+
+impl<'a> VisibleWorld for LitForViewer<'a> {
+    type VisionDistance = vision_distance::Circle;
+
+    fn size(&self) -> Size {
+        self.world.size()
+    }
+
+    fn get_opacity(&self, coord: Coord) -> u8 {
+        self.world.get_opacity(coord)
+    }
+
+    fn for_each_light_by_coord<F: FnMut(Coord, &Light<Self::VisionDistance>)>(&self, mut f: F) {
+        for (entity, light) in self.world.components.light.iter() {
+            if let Some(location) = self.world.spatial_table.location_of(entity) {
+                if location.layer != Some(Layer::Character) || location.coord == self.eye {
+                    f(location.coord, light);
+                }
+            }
+        }
+    }
+}
+
+// What a creature other than the player can see. Creatures see by the same rules as the player,
+// so they can't see through walls or closed doors, and their view is obscured by grass.
+#[derive(Clone)]
+pub struct Perception {
+    vision_distance: vision_distance::Circle,
+    visibility_grid: VisibilityGrid,
+}
+
+// The visibility grid is too large to be worth printing
+impl fmt::Debug for Perception {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Perception")
+            .field(
+                "vision_distance_squared",
+                &self.vision_distance.distance_squared(),
+            )
+            .finish()
+    }
+}
+
+impl Perception {
+    pub fn new(vision_distance: vision_distance::Circle, world_size: Size) -> Self {
+        Self {
+            vision_distance,
+            visibility_grid: VisibilityGrid::new(world_size),
+        }
+    }
+
+    pub fn vision_distance(&self) -> vision_distance::Circle {
+        self.vision_distance
+    }
+
+    // Recompute what the creature can see from its current position
+    pub fn update(&mut self, world: &World, eye: Coord) {
+        self.visibility_grid.update(
+            Rgb24::new_grey(0),
+            &LitForViewer { world, eye },
+            self.vision_distance,
+            eye,
+        );
+    }
+
+    // Returns true iff the creature would notice another creature at the given coord. Creatures
+    // in dim light can only be noticed from close by, and creatures hidden in grass only from
+    // very close by. Light carried by the other creature doesn't count (see `LitForViewer`).
+    pub fn can_spot(&self, world: &World, eye: Coord, coord: Coord) -> bool {
+        let light_colour = match self.visibility_grid.get_visibility(coord) {
+            // Cells which no light reaches are dark, but may still be seen
+            CellVisibility::Current { light_colour, .. } => {
+                light_colour.unwrap_or(Rgb24::new_grey(0))
+            }
+            _ => return false,
+        };
+        let spot_distance_squared =
+            (self.vision_distance.distance_squared() * light_colour.max_channel() as u32 / 255)
+                .max(MIN_SPOT_DISTANCE_SQUARED);
+        let spot_distance_squared = if world.is_concealed_at(coord) {
+            spot_distance_squared.min(CONCEALED_SPOT_DISTANCE_SQUARED)
+        } else {
+            spot_distance_squared
+        };
+        (coord - eye).magnitude2() <= spot_distance_squared
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,142 @@
+use super::{Perception, CONCEALED_SPOT_DISTANCE_SQUARED};
+use crate::{
+    game::{components::EntityData, Config, Game, Layer, World},
+    map_file::MapFile,
+};
+use gridbugs::{
+    coord_2d::{Coord, Size},
+    direction::CardinalDirection,
+    rgb_int::Rgb24,
+    visible_area_detection::{vision_distance::Circle, Light, Rational},
+};
+use rand::SeedableRng;
+use rand_isaac::Isaac64Rng;
+
+const VISION_DISTANCE_SQUARED: u32 = 64;
+
+// Returns the furthest distance at which a creature looking along an empty, open corridor spots
+// the player. The corridor is dark, apart from an optional lamp at the end where the creature
+// stands.
+fn furthest_spotted(lamp: bool) -> i32 {
+    let size = Size::new(20, 3);
+    let mut rng = Isaac64Rng::seed_from_u64(0);
+    let mut world = World::new(size);
+    for coord in size.coord_iter_row_major() {
+        world.spawn_from_template("floor", coord, &mut rng).unwrap();
+    }
+    let eye = Coord::new(0, 1);
+    if lamp {
+        let light = Light {
+            colour: Rgb24::new_grey(255),
+            vision_distance: Circle::new_squared(400),
+            diminish: Rational {
+                numerator: 1,
+                denominator: 100,
+            },
+        };
+        let lamp_data = EntityData {
+            light: Some(light),
+            ..Default::default()
+        };
+        world
+            .spawn_entity((eye, Layer::Feature), lamp_data)
+            .unwrap();
+    }
+    // The player carries their own light, which mustn't give them away
+    let player = world
+        .spawn_from_template("player", Coord::new(1, 1), &mut rng)
+        .unwrap();
+    let mut perception = Perception::new(Circle::new_squared(VISION_DISTANCE_SQUARED), size);
+    let mut furthest = 0;
+    for x in 1..size.width() as i32 {
+        let coord = Coord::new(x, 1);
+        world.move_entity(player, coord);
+        perception.update(&world, eye);
+        if perception.can_spot(&world, eye, coord) {
+            furthest = x;
+        }
+    }
+    furthest
+}
+
+#[test]
+fn creatures_in_the_dark_are_spotted_from_closer() {
+    let dark = furthest_spotted(false);
+    let lit = furthest_spotted(true);
+    assert!(
+        dark < lit,
+        "spotted at {} in the dark and {} in the light",
+        dark,
+        lit
+    );
+    // Creatures can always spot their neighbours, and can see as far as the light allows
+    assert_eq!(dark, 1);
+    assert_eq!(lit * lit, VISION_DISTANCE_SQUARED as i32);
+}
+
+// A game in a brightly lit corridor, with the player at the west end next to a patch of grass
+fn grass_corridor_game() -> Game {
+    let map = MapFile::parse("##########\n#@\"......#\n##########\n").unwrap();
+    let config = Config {
+        omniscient: false,
+        rng_seed: Some("test".parse().unwrap()),
+        generator: Default::default(),
+        generation_params: Default::default(),
+        map: Some(map),
+    };
+    let mut game = Game::new(Size::new(20, 15), config);
+    let light = Light {
+        colour: Rgb24::new_grey(255),
+        vision_distance: Circle::new_squared(400),
+        diminish: Rational {
+            numerator: 1,
+            denominator: 1000,
+        },
+    };
+    let lamp_data = EntityData {
+        light: Some(light),
+        ..Default::default()
+    };
+    game.world
+        .spawn_entity((Coord::new(8, 1), Layer::Feature), lamp_data)
+        .unwrap();
+    game
+}
+
+// Returns the squared distances from which a creature looking along the corridor spots the player
+fn spotted_from(game: &Game) -> Vec<u32> {
+    let player_coord = game.get_player_coord();
+    let mut perception = Perception::new(
+        Circle::new_squared(VISION_DISTANCE_SQUARED),
+        game.world.spatial_table.grid_size(),
+    );
+    (3..9)
+        .map(|x| Coord::new(x, 1))
+        .filter(|&eye| {
+            perception.update(&game.world, eye);
+            perception.can_spot(&game.world, eye, player_coord)
+        })
+        .map(|eye| (eye - player_coord).magnitude2())
+        .collect()
+}
+
+#[test]
+fn players_standing_in_grass_are_only_spotted_from_close_by() {
+    let mut game = grass_corridor_game();
+    game.move_player(CardinalDirection::East);
+    assert!(game.is_player_concealed());
+    let spotted = spotted_from(&game);
+    assert!(!spotted.is_empty());
+    assert!(
+        spotted
+            .iter()
+            .all(|&distance2| distance2 <= CONCEALED_SPOT_DISTANCE_SQUARED),
+        "spotted from {:?}",
+        spotted
+    );
+    // Leaving the grass crushes it, so it no longer hides the player
+    game.move_player(CardinalDirection::West);
+    game.move_player(CardinalDirection::East);
+    assert!(!game.is_player_concealed());
+    assert_eq!(spotted_from(&game).len(), 6);
+}
@@ -0,0 +1,269 @@
+use super::{
+    ai::{is_badly_wounded, Ai},
+    stealth::{Noise, IMPACT_NOISE},
+    Game, Tile, World,
+};
+use crate::{realtime::types as realtime_types, rng::RngStream};
+use gridbugs::{coord_2d::Coord, entity_table::Entity, visible_area_detection::CellVisibility};
+use log::debug;
+
+const PROJECTILE_DAMAGE: u32 = 1;
+
+// What a projectile did when it landed
+#[derive(Clone, Copy, Debug)]
+pub enum ShotOutcome {
+    Missed,
+    // The creature with the given tile was hit but survived
+    Hit(Tile),
+    Killed(Tile),
+}
+
+// A projectile which has been fired but hasn't yet landed
+#[derive(Clone, Copy, Debug)]
+pub struct ProjectileInFlight {
+    entity: Entity,
+    // Where the projectile will land
+    impact: Coord,
+}
+
+// The cells on a straight line from one coord to another, excluding `from` and including `to`,
+// found with Bresenham's algorithm
+fn line_to(from: Coord, to: Coord) -> impl Iterator<Item = Coord> {
+    let delta = to - from;
+    let step = Coord::new(delta.x.signum(), delta.y.signum());
+    let (dx, dy) = (delta.x.abs(), delta.y.abs());
+    let mut error = dx - dy;
+    let mut current = from;
+    std::iter::from_fn(move || {
+        let doubled_error = 2 * error;
+        if doubled_error > -dy {
+            error -= dy;
+            current.x += step.x;
+        }
+        if doubled_error < dx {
+            error += dx;
+            current.y += step.y;
+        }
+        Some(current)
+    })
+    .take(dx.max(dy) as usize)
+}
+
+// What a projectile passing through a cell needs to know about it
+#[derive(Default)]
+struct FireCell {
+    solid: bool,
+    opacity: u8,
+    creature: bool,
+}
+
+// The cells a projectile passes through on its way from one coord towards another, where `cell`
+// describes each cell, or returns `None` outside the level. The path ends before the first solid
+// cell, at the first creature, or once the cells passed through have blocked as much as a wall
+// would.
+fn line_of_fire<F: Fn(Coord) -> Option<FireCell>>(from: Coord, to: Coord, cell: F) -> Vec<Coord> {
+    let mut path = Vec::new();
+    let mut total_opacity = 0;
+    for coord in line_to(from, to) {
+        let Some(cell) = cell(coord) else {
+            break;
+        };
+        if cell.solid {
+            break;
+        }
+        total_opacity += cell.opacity as u32;
+        path.push(coord);
+        if cell.creature || total_opacity >= 255 {
+            break;
+        }
+    }
+    path
+}
+
+impl World {
+    // The path a projectile will actually take from one coord towards another
+    pub fn line_of_fire(&self, from: Coord, to: Coord) -> Vec<Coord> {
+        line_of_fire(from, to, |coord| {
+            let layers = self.spatial_table.layers_at(coord)?;
+            let mut cell = FireCell {
+                creature: layers.character.is_some(),
+                ..Default::default()
+            };
+            if let Some(feature_entity) = layers.feature {
+                cell.solid = self.components.solid.contains(feature_entity);
+                cell.opacity = self
+                    .components
+                    .opacity
+                    .get(feature_entity)
+                    .cloned()
+                    .unwrap_or(0);
+            }
+            Some(cell)
+        })
+    }
+}
+
+impl Game {
+    // The path the player expects a projectile fired at the given coord to take, based on what
+    // they have seen. Cells the player hasn't seen are assumed to be open, and only creatures in
+    // view are expected to stop the projectile.
+    pub fn line_of_fire(&self, target: Coord) -> Vec<Coord> {
+        let world_size = self.world_size();
+        line_of_fire(self.get_player_coord(), target, |coord| {
+            if !coord.is_valid(world_size) {
+                return None;
+            }
+            let Some(data) = self.visibility_grid.get_data(coord) else {
+                return Some(FireCell::default());
+            };
+            let feature = data.entity_data.feature.as_ref();
+            let creature = match self.visibility_grid.get_visibility(coord) {
+                CellVisibility::Current { data, .. } => data.entity_data.character.is_some(),
+                _ => false,
+            };
+            Some(FireCell {
+                solid: feature.is_some_and(|entity_data| entity_data.solid),
+                opacity: feature.map_or(0, |entity_data| entity_data.opacity),
+                creature,
+            })
+        })
+    }
+
+    // The coords of every creature the player can currently see other than the player, nearest
+    // first
+    pub fn visible_creature_coords(&self) -> Vec<Coord> {
+        let player_coord = self.get_player_coord();
+        let mut coords = self
+            .visibility_grid()
+            .enumerate()
+            .filter_map(|(coord, visibility)| match visibility {
+                CellVisibility::Current { data, .. } => data
+                    .entity_data
+                    .character
+                    .as_ref()
+                    .filter(|entity_data| !matches!(entity_data.tile, Tile::Player))
+                    .map(|_| coord),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        coords.sort_by_key(|&coord| ((coord - player_coord).magnitude2(), coord.y, coord.x));
+        coords
+    }
+
+    pub fn is_projectile_in_flight(&self) -> bool {
+        self.projectile_in_flight.is_some()
+    }
+
+    // Remove a projectile which is still in flight without resolving what it would have hit
+    pub(super) fn cancel_projectile(&mut self) {
+        if let Some(projectile) = self.projectile_in_flight.take() {
+            self.world.remove_entity(projectile.entity);
+        }
+    }
+
+    // Fire a projectile from the player towards the given coord. The projectile is animated
+    // across the level, and the player's turn ends once it lands.
+    pub fn fire_projectile(&mut self, target: Coord) -> Result<(), String> {
+        if self.is_projectile_in_flight() {
+            return Err("a projectile is already in flight".to_string());
+        }
+        let player_coord = self.get_player_coord();
+        if target == player_coord {
+            return Err("you can't aim at yourself".to_string());
+        }
+        // The player aims at what they can see, but the projectile hits what is really there
+        let path = self.world.line_of_fire(player_coord, target);
+        let Some(&impact) = path.last() else {
+            return Err("there is something solid right in front of you".to_string());
+        };
+        let entity = self
+            .world
+            .spawn_from_template(
+                "projectile",
+                player_coord,
+                self.rngs.get(RngStream::Spawning),
+            )
+            .map_err(|e| format!("failed to spawn projectile: {}", e))?;
+        self.world.components.realtime.insert(entity, ());
+        self.world
+            .realtime_components
+            .projectile
+            .insert(entity, realtime_types::ProjectileState::new(path));
+        debug!("Player fired at {:?}, landing at {:?}", target, impact);
+        self.projectile_in_flight = Some(ProjectileInFlight { entity, impact });
+        Ok(())
+    }
+
+    // Once the projectile's animation has finished, remove it and resolve what it hit
+    pub(super) fn resolve_landed_projectile(&mut self) {
+        let Some(projectile) = self.projectile_in_flight else {
+            return;
+        };
+        if self
+            .world
+            .realtime_components
+            .projectile
+            .contains(projectile.entity)
+        {
+            return;
+        }
+        self.projectile_in_flight = None;
+        self.world.remove_entity(projectile.entity);
+        let target_entity = self
+            .world
+            .spatial_table
+            .layers_at(projectile.impact)
+            .and_then(|layers| layers.character);
+        let shot_outcome = match target_entity {
+            Some(target_entity) => self.projectile_hit(target_entity),
+            None => ShotOutcome::Missed,
+        };
+        debug!(
+            "Projectile landed at {:?}: {:?}",
+            projectile.impact, shot_outcome
+        );
+        self.shot_outcome = Some(shot_outcome);
+        self.make_noise(Noise {
+            source: projectile.impact,
+            loudness: IMPACT_NOISE,
+        });
+        self.end_player_turn();
+    }
+
+    // Returns (and forgets) what the player's last projectile did, once it has landed
+    pub fn take_shot_outcome(&mut self) -> Option<ShotOutcome> {
+        self.shot_outcome.take()
+    }
+
+    // Damage a creature hit by a projectile. Monsters which survive know where the shot came from.
+    fn projectile_hit(&mut self, entity: Entity) -> ShotOutcome {
+        let player_coord = self.get_player_coord();
+        let tile = self.world.components.tile.get(entity).cloned();
+        let tile = tile.expect("creature hit by projectile has no tile");
+        let Some(health) = self.world.components.health.get_mut(entity) else {
+            return ShotOutcome::Hit(tile);
+        };
+        health.current = health.current.saturating_sub(PROJECTILE_DAMAGE);
+        debug!(
+            "Projectile hit {:?} ({}/{} health remaining)",
+            entity, health.current, health.max
+        );
+        if health.current == 0 {
+            debug!("{:?} was killed by a projectile", entity);
+            self.world.remove_entity(entity);
+            return ShotOutcome::Killed(tile);
+        }
+        let ai = if is_badly_wounded(health) {
+            Ai::Fleeing(player_coord)
+        } else {
+            Ai::Hunting(player_coord)
+        };
+        if let Some(current_ai) = self.world.components.ai.get_mut(entity) {
+            *current_ai = ai;
+        }
+        ShotOutcome::Hit(tile)
+    }
+}
+
+#[cfg(test)]
+mod test;
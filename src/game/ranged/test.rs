@@ -0,0 +1,90 @@
+use super::ShotOutcome;
+use crate::{
+    game::{Config, Game, Tile},
+    map_file::MapFile,
+};
+use gridbugs::coord_2d::{Coord, Size};
+use rand::SeedableRng;
+use rand_isaac::Isaac64Rng;
+
+// A game in a corridor, with the player at the west end
+fn corridor_game() -> Game {
+    let map = MapFile::parse("#########\n#@......#\n#########\n").unwrap();
+    let config = Config {
+        omniscient: false,
+        rng_seed: Some("test".parse().unwrap()),
+        generator: Default::default(),
+        generation_params: Default::default(),
+        map: Some(map),
+    };
+    Game::new(Size::new(20, 15), config)
+}
+
+fn spawn_goblin(game: &mut Game, coord: Coord) {
+    let mut rng = Isaac64Rng::seed_from_u64(0);
+    game.world
+        .spawn_from_template("goblin", coord, &mut rng)
+        .unwrap();
+}
+
+// Run the animation until the projectile lands, and return what it did
+fn land_projectile(game: &mut Game) -> Option<ShotOutcome> {
+    for _ in 0..1000 {
+        game.animation_tick();
+        if !game.is_projectile_in_flight() {
+            return game.take_shot_outcome();
+        }
+    }
+    panic!("projectile never landed");
+}
+
+#[test]
+fn aiming_at_yourself_is_rejected() {
+    let mut game = corridor_game();
+    let e = game.fire_projectile(game.get_player_coord()).unwrap_err();
+    assert!(e.contains("yourself"), "{}", e);
+}
+
+#[test]
+fn firing_into_a_wall_next_to_you_is_rejected() {
+    let mut game = corridor_game();
+    let e = game.fire_projectile(Coord::new(1, 0)).unwrap_err();
+    assert!(e.contains("solid"), "{}", e);
+}
+
+#[test]
+fn predicted_path_only_stops_at_creatures_the_player_can_see() {
+    let mut game = corridor_game();
+    let goblin_coord = Coord::new(4, 1);
+    let target = Coord::new(7, 1);
+    spawn_goblin(&mut game, goblin_coord);
+    // The player hasn't looked since the goblin arrived
+    assert_eq!(game.line_of_fire(target).last(), Some(&target));
+    let player_coord = game.get_player_coord();
+    assert_eq!(
+        game.world.line_of_fire(player_coord, target).last(),
+        Some(&goblin_coord)
+    );
+    game.update_visibility();
+    assert_eq!(game.line_of_fire(target).last(), Some(&goblin_coord));
+}
+
+#[test]
+fn shots_are_reported() {
+    let mut game = corridor_game();
+    game.fire_projectile(Coord::new(7, 1)).unwrap();
+    assert!(matches!(
+        land_projectile(&mut game),
+        Some(ShotOutcome::Missed)
+    ));
+    let goblin_coord = Coord::new(4, 1);
+    spawn_goblin(&mut game, goblin_coord);
+    game.update_visibility();
+    game.fire_projectile(goblin_coord).unwrap();
+    assert!(matches!(
+        land_projectile(&mut game),
+        Some(ShotOutcome::Hit(Tile::Goblin))
+    ));
+    // The outcome is only reported once
+    assert!(game.take_shot_outcome().is_none());
+}
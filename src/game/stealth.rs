@@ -0,0 +1,72 @@
+use super::{distance_map::DistanceMap, DoorState, GrassState, Tile, World};
+use gridbugs::coord_2d::Coord;
+
+// How many cells away the noise of each action can be heard
+pub const GRASS_NOISE: u32 = 4;
+pub const WATER_NOISE: u32 = 6;
+pub const DOOR_NOISE: u32 = 8;
+pub const IMPACT_NOISE: u32 = 6;
+
+// Noise loses this much extra loudness passing through a closed door
+const CLOSED_DOOR_NOISE_COST: u32 = 4;
+
+// Creatures standing in grass which hasn't been crushed can only be spotted from this close
+pub const CONCEALED_SPOT_DISTANCE_SQUARED: u32 = 2 * 2;
+
+// A sound made somewhere in the world
+#[derive(Clone, Copy, Debug)]
+pub struct Noise {
+    pub source: Coord,
+    // The distance the noise travels through open space
+    pub loudness: u32,
+}
+
+impl World {
+    // Returns true iff a creature at the given coord is hidden by grass. Crushed grass is too
+    // flat to hide in.
+    pub fn is_concealed_at(&self, coord: Coord) -> bool {
+        self.spatial_table
+            .layers_at(coord)
+            .and_then(|layers| layers.feature)
+            .and_then(|feature_entity| self.components.grass_state.get(feature_entity))
+            .is_some_and(|grass_state| matches!(grass_state, GrassState::Normal))
+    }
+
+    pub fn is_water_at(&self, coord: Coord) -> bool {
+        self.spatial_table
+            .layers_at(coord)
+            .and_then(|layers| layers.floor)
+            .and_then(|floor_entity| self.components.tile.get(floor_entity))
+            .is_some_and(|tile| matches!(tile, Tile::Water))
+    }
+
+    // How much it costs for noise to enter a cell, or `None` if noise can't pass through it
+    fn noise_cost(&self, coord: Coord) -> Option<u32> {
+        let layers = self.spatial_table.layers_at(coord)?;
+        match layers.feature {
+            Some(feature_entity) => match self.components.door_state.get(feature_entity) {
+                Some(DoorState::Closed) => Some(1 + CLOSED_DOOR_NOISE_COST),
+                Some(DoorState::Open) => Some(1),
+                None if self.components.solid.contains(feature_entity) => None,
+                None => Some(1),
+            },
+            None => Some(1),
+        }
+    }
+
+    // Returns every cell the noise reaches. Noise spreads around walls rather than through them,
+    // so a noise can be loud on one side of a wall and silent on the other.
+    pub fn cells_reached_by_noise(&self, noise: Noise) -> Vec<Coord> {
+        let distance_map = DistanceMap::compute(
+            self.spatial_table.grid_size(),
+            &[noise.source],
+            |coord| self.noise_cost(coord),
+            noise.loudness,
+        );
+        self.spatial_table
+            .grid_size()
+            .coord_iter_row_major()
+            .filter(|&coord| distance_map.distance(coord).is_some())
+            .collect()
+    }
+}
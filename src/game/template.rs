@@ -0,0 +1,327 @@
+use super::{ai::Ai, components::EntityData, DoorState, GrassState, Health, Layer, Tile};
+use gridbugs::{
+    rgb_int::Rgb24,
+    visible_area_detection::{vision_distance::Circle, Light, Rational},
+};
+use std::{collections::HashMap, fs, path::Path, sync::OnceLock};
+
+// Templates bundled with the game. See the comment at the top of the file for the format.
+const BUNDLED_TEMPLATES: &str = include_str!("entity_templates.txt");
+
+// A realtime animation which can be attached to an entity by its template. Animations are driven
+// by code in the realtime module, so unlike tiles, new ones can't be described by a template alone.
+#[derive(Clone, Copy, Debug)]
+pub enum Animation {
+    Water,
+}
+
+// Everything needed to spawn a particular kind of entity
+#[derive(Clone)]
+pub struct EntityTemplate {
+    pub layer: Layer,
+    pub entity_data: EntityData,
+    pub animation: Option<Animation>,
+    // Creatures with a vision distance see the world for themselves
+    pub vision_distance: Option<Circle>,
+}
+
+fn parse_layer(value: &str) -> Result<Layer, String> {
+    match value {
+        "projectile" => Ok(Layer::Projectile),
+        "character" => Ok(Layer::Character),
+        "feature" => Ok(Layer::Feature),
+        "floor" => Ok(Layer::Floor),
+        other => Err(format!("unknown layer: {}", other)),
+    }
+}
+
+// Parse a tile drawn as a single character, of the form "<character> <r> <g> <b>". Its name is
+// filled in from the template once the template is complete.
+fn parse_glyph(value: &str) -> Result<Tile, String> {
+    let (character, colour) = value
+        .split_once(' ')
+        .ok_or_else(|| format!("expected \"glyph <character> <r> <g> <b>\": {}", value))?;
+    let mut chars = character.chars();
+    let (Some(character), None) = (chars.next(), chars.next()) else {
+        return Err(format!("expected a single character: {}", character));
+    };
+    Ok(Tile::Glyph {
+        character,
+        colour: parse_rgb24(colour.trim())?,
+        name: "",
+    })
+}
+
+fn parse_tile(value: &str) -> Result<Tile, String> {
+    if let Some(glyph) = value.strip_prefix("glyph ") {
+        return parse_glyph(glyph.trim());
+    }
+    match value {
+        "player" => Ok(Tile::Player),
+        "goblin" => Ok(Tile::Goblin),
+        "projectile" => Ok(Tile::Projectile),
+        "wall" => Ok(Tile::Wall),
+        "door_open" => Ok(Tile::DoorOpen),
+        "door_closed" => Ok(Tile::DoorClosed),
+        "floor" => Ok(Tile::Floor),
+        "cave_wall" => Ok(Tile::CaveWall),
+        "cave_floor" => Ok(Tile::CaveFloor),
+        "grass" => Ok(Tile::Grass),
+        "grass_crushed" => Ok(Tile::GrassCrushed),
+        "water" => Ok(Tile::Water),
+        other => Err(format!("unknown tile: {}", other)),
+    }
+}
+
+fn parse_door_state(value: &str) -> Result<DoorState, String> {
+    match value {
+        "open" => Ok(DoorState::Open),
+        "closed" => Ok(DoorState::Closed),
+        other => Err(format!("unknown door state: {}", other)),
+    }
+}
+
+fn parse_grass_state(value: &str) -> Result<GrassState, String> {
+    match value {
+        "normal" => Ok(GrassState::Normal),
+        "crushed" => Ok(GrassState::Crushed),
+        other => Err(format!("unknown grass state: {}", other)),
+    }
+}
+
+fn parse_animation(value: &str) -> Result<Animation, String> {
+    match value {
+        "water" => Ok(Animation::Water),
+        other => Err(format!("unknown animation: {}", other)),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid number: {}", value))
+}
+
+// Parse a colour of the form "<r> <g> <b>"
+fn parse_rgb24(value: &str) -> Result<Rgb24, String> {
+    let channels = value
+        .split_whitespace()
+        .map(parse_number)
+        .collect::<Result<Vec<u8>, _>>()?;
+    match channels.as_slice() {
+        &[r, g, b] => Ok(Rgb24::new(r, g, b)),
+        _ => Err(format!("expected \"<r> <g> <b>\": {}", value)),
+    }
+}
+
+// Parse a fraction of the form "<numerator>/<denominator>"
+fn parse_rational(value: &str) -> Result<Rational, String> {
+    let (numerator, denominator) = value
+        .split_once('/')
+        .ok_or_else(|| format!("expected \"<numerator>/<denominator>\": {}", value))?;
+    Ok(Rational {
+        numerator: parse_number(numerator.trim())?,
+        denominator: parse_number(denominator.trim())?,
+    })
+}
+
+// Accumulates the properties of a template as its lines are parsed
+#[derive(Default)]
+struct TemplateBuilder {
+    layer: Option<Layer>,
+    entity_data: EntityData,
+    animation: Option<Animation>,
+    vision_distance: Option<Circle>,
+    light_colour: Option<Rgb24>,
+    light_distance_squared: Option<u32>,
+    light_diminish: Option<Rational>,
+}
+
+impl TemplateBuilder {
+    fn add_property(&mut self, key: &str, value: Option<&str>) -> Result<(), String> {
+        let entity_data = &mut self.entity_data;
+        match (key, value) {
+            ("solid", None) => entity_data.solid = Some(()),
+            ("ai", None) => entity_data.ai = Some(Ai::Idle),
+            (_, None) => return Err(format!("expected \"{} = <value>\"", key)),
+            ("layer", Some(value)) => self.layer = Some(parse_layer(value)?),
+            ("tile", Some(value)) => entity_data.tile = Some(parse_tile(value)?),
+            ("opacity", Some(value)) => entity_data.opacity = Some(parse_number(value)?),
+            ("door", Some(value)) => entity_data.door_state = Some(parse_door_state(value)?),
+            ("grass", Some(value)) => entity_data.grass_state = Some(parse_grass_state(value)?),
+            ("light_colour", Some(value)) => self.light_colour = Some(parse_rgb24(value)?),
+            ("light_distance_squared", Some(value)) => {
+                self.light_distance_squared = Some(parse_number(value)?)
+            }
+            ("light_diminish", Some(value)) => self.light_diminish = Some(parse_rational(value)?),
+            ("animation", Some(value)) => {
+                self.animation = Some(parse_animation(value)?);
+                entity_data.realtime = Some(());
+            }
+            ("vision_distance_squared", Some(value)) => {
+                self.vision_distance = Some(Circle::new_squared(parse_number(value)?))
+            }
+            ("health", Some(value)) => {
+                let max = parse_number(value)?;
+                entity_data.health = Some(Health { current: max, max });
+            }
+            (other, Some(_)) => return Err(format!("unknown property: {}", other)),
+        }
+        Ok(())
+    }
+
+    fn build(self, name: &str) -> Result<EntityTemplate, String> {
+        let Self {
+            layer,
+            mut entity_data,
+            animation,
+            vision_distance,
+            light_colour,
+            light_distance_squared,
+            light_diminish,
+        } = self;
+        let layer = layer.ok_or_else(|| "missing layer".to_string())?;
+        match entity_data.tile.as_mut() {
+            None => return Err("missing tile".to_string()),
+            Some(Tile::Glyph {
+                name: glyph_name, ..
+            }) => {
+                // Templates are kept for the rest of the program, so leaking their names is fine
+                *glyph_name = Box::leak(name.to_string().into_boxed_str());
+            }
+            Some(_) => (),
+        }
+        // Some tiles are drawn or used in ways which rely on other components
+        match entity_data.tile {
+            Some(Tile::Water) if !matches!(animation, Some(Animation::Water)) => {
+                return Err("water tiles need \"animation = water\"".to_string());
+            }
+            Some(Tile::Player) if entity_data.health.is_none() => {
+                return Err("player tiles need health".to_string());
+            }
+            _ => (),
+        }
+        match (light_colour, light_distance_squared, light_diminish) {
+            (Some(colour), Some(distance_squared), Some(diminish)) => {
+                entity_data.light = Some(Light {
+                    colour,
+                    vision_distance: Circle::new_squared(distance_squared),
+                    diminish,
+                });
+            }
+            (None, None, None) => (),
+            _ => return Err("lights need a colour, distance and diminish".to_string()),
+        }
+        Ok(EntityTemplate {
+            layer,
+            entity_data,
+            animation,
+            vision_distance,
+        })
+    }
+}
+
+// A collection of entity templates, looked up by name
+pub struct EntityTemplates {
+    templates: HashMap<String, EntityTemplate>,
+}
+
+// The templates shared by every world
+static TEMPLATES: OnceLock<EntityTemplates> = OnceLock::new();
+
+impl EntityTemplates {
+    // Parse templates from text of the form:
+    //
+    // # comment
+    // [wall]
+    // layer = feature
+    // tile = wall
+    // solid
+    // opacity = 255
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut templates = HashMap::new();
+        let mut current: Option<(String, TemplateBuilder)> = None;
+        let finish = |templates: &mut HashMap<_, _>, (name, builder): (String, TemplateBuilder)| {
+            let template = builder
+                .build(&name)
+                .map_err(|e| format!("invalid template {}: {}", name, e))?;
+            if templates.insert(name.clone(), template).is_some() {
+                return Err(format!("multiple templates named {}", name));
+            }
+            Ok(())
+        };
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|n| n.strip_suffix(']')) {
+                if let Some(previous) = current.take() {
+                    finish(&mut templates, previous)?;
+                }
+                current = Some((name.trim().to_string(), TemplateBuilder::default()));
+                continue;
+            }
+            let (_, builder) = current
+                .as_mut()
+                .ok_or_else(|| format!("property outside template at line {}", i + 1))?;
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), Some(value.trim())),
+                None => (line, None),
+            };
+            builder
+                .add_property(key, value)
+                .map_err(|e| format!("{} at line {}", e, i + 1))?;
+        }
+        if let Some(last) = current {
+            finish(&mut templates, last)?;
+        }
+        Ok(Self { templates })
+    }
+
+    fn bundled() -> Self {
+        Self::parse(BUNDLED_TEMPLATES)
+            .unwrap_or_else(|e| panic!("invalid bundled entity templates: {}", e))
+    }
+
+    // Read templates from a file, in the same format as `parse`. They are added to the templates
+    // bundled with the game, replacing any bundled templates with the same name.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let loaded = Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut templates = Self::bundled();
+        templates.templates.extend(loaded.templates);
+        // The player is spawned from the template named "player" whatever its tile, and the game
+        // relies on them being a character with health
+        let player = templates.get("player").unwrap();
+        if !matches!(player.layer, Layer::Character) || player.entity_data.health.is_none() {
+            return Err(format!(
+                "{}: the player template needs \"layer = character\" and health",
+                path.display()
+            ));
+        }
+        Ok(templates)
+    }
+
+    // Use the given templates for every world created from now on. This must be called before
+    // the first world is created.
+    pub fn install(self) -> Result<(), String> {
+        TEMPLATES
+            .set(self)
+            .map_err(|_| "entity templates are already in use".to_string())
+    }
+
+    // Returns the installed templates, or the bundled templates if none were installed
+    pub fn current() -> &'static Self {
+        TEMPLATES.get_or_init(Self::bundled)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&EntityTemplate> {
+        self.templates.get(name)
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,171 @@
+use super::{EntityTemplates, Layer, Tile};
+use std::{fs, path::PathBuf};
+
+// Writes a templates file to a unique path in the temporary directory
+fn write_templates_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "roguelike-templates-test-{}-{}.txt",
+        std::process::id(),
+        name
+    ));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn bundled_templates_are_valid() {
+    // Panics if the bundled templates fail to parse
+    assert!(EntityTemplates::bundled().get("player").is_some());
+}
+
+#[test]
+fn templates_are_parsed() {
+    let templates = EntityTemplates::parse(
+        "# a comment\n[lamp]\nlayer = feature\ntile = wall\nsolid\nopacity = 100\n\
+         light_colour = 255 127 0\nlight_distance_squared = 20\nlight_diminish = 1/10\n",
+    )
+    .unwrap();
+    let lamp = templates.get("lamp").unwrap();
+    assert!(matches!(lamp.layer, Layer::Feature));
+    assert!(matches!(lamp.entity_data.tile, Some(Tile::Wall)));
+    assert!(lamp.entity_data.solid.is_some());
+    assert_eq!(lamp.entity_data.opacity, Some(100));
+    assert!(lamp.entity_data.light.is_some());
+    assert!(templates.get("player").is_none());
+}
+
+#[test]
+fn glyph_tiles_are_named_after_their_template() {
+    let templates =
+        EntityTemplates::parse("[rat]\nlayer = character\ntile = glyph r 127 95 63\n").unwrap();
+    match templates.get("rat").unwrap().entity_data.tile {
+        Some(Tile::Glyph {
+            character,
+            colour,
+            name,
+        }) => {
+            assert_eq!(character, 'r');
+            assert_eq!((colour.r, colour.g, colour.b), (127, 95, 63));
+            assert_eq!(name, "rat");
+        }
+        other => panic!("unexpected tile: {:?}", other),
+    }
+}
+
+#[test]
+fn unknown_keys_are_rejected() {
+    let e = EntityTemplates::parse("[rat]\nlayer = character\ntile = goblin\nsmell = bad\n")
+        .err()
+        .unwrap();
+    assert!(e.contains("unknown property: smell"), "{}", e);
+    assert!(e.contains("line 4"), "{}", e);
+}
+
+#[test]
+fn templates_missing_a_layer_or_tile_are_rejected() {
+    let e = EntityTemplates::parse("[rat]\ntile = goblin\n")
+        .err()
+        .unwrap();
+    assert!(e.contains("missing layer"), "{}", e);
+    let e = EntityTemplates::parse("[rat]\nlayer = character\n")
+        .err()
+        .unwrap();
+    assert!(e.contains("missing tile"), "{}", e);
+}
+
+#[test]
+fn partial_lights_are_rejected() {
+    for light in [
+        "light_colour = 255 255 255\n",
+        "light_colour = 255 255 255\nlight_distance_squared = 10\n",
+        "light_distance_squared = 10\nlight_diminish = 1/10\n",
+        "light_diminish = 1/10\n",
+    ] {
+        let text = format!("[lamp]\nlayer = feature\ntile = wall\n{}", light);
+        let e = EntityTemplates::parse(&text).err().unwrap();
+        assert!(e.contains("lights need"), "{}", e);
+    }
+}
+
+#[test]
+fn invalid_values_are_rejected() {
+    for property in [
+        "layer = sky",
+        "tile = dragon",
+        "tile = glyph rat 255 0 0",
+        "tile = glyph r 255 0",
+        "opacity = 256",
+        "door = ajar",
+        "light_diminish = 1",
+        "health",
+    ] {
+        let text = format!("[thing]\nlayer = feature\ntile = wall\n{}\n", property);
+        assert!(
+            EntityTemplates::parse(&text).is_err(),
+            "accepted {}",
+            property
+        );
+    }
+}
+
+#[test]
+fn duplicate_templates_and_properties_outside_templates_are_rejected() {
+    let template = "[rat]\nlayer = character\ntile = goblin\n";
+    assert!(EntityTemplates::parse(&format!("{}{}", template, template)).is_err());
+    assert!(EntityTemplates::parse(&format!("solid\n{}", template)).is_err());
+}
+
+#[test]
+fn loaded_templates_are_added_to_the_bundled_ones() {
+    let path = write_templates_file(
+        "load",
+        "[goblin]\nlayer = character\ntile = goblin\nhealth = 7\n\n\
+         [rat]\nlayer = character\ntile = glyph r 127 95 63\nhealth = 1\nai\n",
+    );
+    let templates = EntityTemplates::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(templates.get("player").is_some());
+    assert!(templates.get("rat").is_some());
+    let goblin_health = templates.get("goblin").unwrap().entity_data.health.unwrap();
+    assert_eq!(goblin_health.max, 7);
+}
+
+#[test]
+fn load_reports_the_file_with_the_error() {
+    let path = write_templates_file("invalid", "[rat]\nlayer = character\n");
+    let e = EntityTemplates::load(&path).err().unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(e.contains(&path.display().to_string()), "{}", e);
+    assert!(EntityTemplates::load(&path).is_err());
+}
+
+#[test]
+fn tiles_missing_components_they_rely_on_are_rejected() {
+    let e = EntityTemplates::parse("[pool]\nlayer = floor\ntile = water\n")
+        .err()
+        .unwrap();
+    assert!(e.contains("animation = water"), "{}", e);
+    let e = EntityTemplates::parse("[hero]\nlayer = character\ntile = player\n")
+        .err()
+        .unwrap();
+    assert!(e.contains("need health"), "{}", e);
+}
+
+#[test]
+fn load_rejects_a_player_which_the_game_cannot_use() {
+    for (name, player) in [
+        (
+            "no-health",
+            "[player]\nlayer = character\ntile = glyph @ 255 255 255\n",
+        ),
+        (
+            "feature",
+            "[player]\nlayer = feature\ntile = player\nhealth = 10\n",
+        ),
+    ] {
+        let path = write_templates_file(name, player);
+        let e = EntityTemplates::load(&path).err().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(e.contains("player template"), "{}", e);
+    }
+}
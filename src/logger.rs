@@ -0,0 +1,67 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use std::{fs::File, io::Write, path::Path, sync::Mutex, time::Instant};
+
+// Records from other crates (such as the graphics backend) are very noisy, so only record them at
+// this level or above
+const DEPENDENCY_MAX_LEVEL: LevelFilter = LevelFilter::Warn;
+
+// Writes log records to a file, one per line. Each record is written immediately so the log is
+// complete even if the game crashes.
+struct FileLogger {
+    file: Mutex<File>,
+    // Records from this crate are written at this level or above
+    max_level: LevelFilter,
+    start: Instant,
+}
+
+impl FileLogger {
+    fn max_level_for_target(&self, target: &str) -> LevelFilter {
+        if target.starts_with(env!("CARGO_CRATE_NAME")) {
+            self.max_level
+        } else {
+            self.max_level.min(DEPENDENCY_MAX_LEVEL)
+        }
+    }
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.max_level_for_target(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut file = self.file.lock().unwrap();
+        // Failing to log shouldn't stop the game, so errors writing to the file are ignored
+        let _ = writeln!(
+            file,
+            "[{:>10.3}] {:<5} {}: {}",
+            self.start.elapsed().as_secs_f64(),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+    }
+
+    fn flush(&self) {
+        let _ = self.file.lock().unwrap().flush();
+    }
+}
+
+// Start writing log records at or above the given level to a file, replacing any existing contents
+// of the file
+pub fn init<P: AsRef<Path>>(path: P, max_level: LevelFilter) -> Result<(), String> {
+    let path = path.as_ref();
+    let file =
+        File::create(path).map_err(|e| format!("failed to create {}: {}", path.display(), e))?;
+    let logger = FileLogger {
+        file: Mutex::new(file),
+        max_level,
+        start: Instant::now(),
+    };
+    log::set_boxed_logger(Box::new(logger)).map_err(|e| e.to_string())?;
+    log::set_max_level(max_level);
+    Ok(())
+}
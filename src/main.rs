@@ -1,48 +1,140 @@
-use gridbugs::{chargrid_ansi_terminal, chargrid_wgpu};
+use gridbugs::{chargrid_ansi_terminal, chargrid_wgpu, coord_2d::Size};
+use log::{info, LevelFilter};
+use seed::Seed;
+use std::path::PathBuf;
+use terrain::{GenerationParams, GeneratorChoice, GeneratorKind};
 
 mod app;
+mod export;
 mod game;
+mod logger;
+mod map_file;
 mod realtime;
+mod rng;
+mod seed;
 mod terrain;
 
 // Command-line arguments
 struct Args {
     terminal: bool,
     omniscient: bool,
-    rng_seed: Option<u64>,
+    debug: bool,
+    rng_seed: Option<Seed>,
+    daily: bool,
+    generator: GeneratorChoice,
+    generation_params: Option<PathBuf>,
+    generation_param_overrides: Vec<String>,
+    print_generation_params: bool,
+    export_map: Option<PathBuf>,
+    export_remembered: bool,
+    map: Option<PathBuf>,
+    entity_templates: Option<PathBuf>,
+    edit: Option<PathBuf>,
+    world_width: u32,
+    world_height: u32,
+    log_file: PathBuf,
+    log_level: LevelFilter,
 }
 
+// Where the log is written unless a path is given on the command line
+const DEFAULT_LOG_FILE: &str = "gridbugs-roguelike-tutorial.log";
+
 impl Args {
     pub fn parser() -> impl meap::Parser<Item = Self> {
         meap::let_map! {
             let {
                 terminal = flag("terminal").desc("run in a terminal");
                 omniscient = flag("omniscient").desc("give the player omniscient vision");
-                rng_seed = opt_opt::<u64, _>("INT", "rng-seed").desc("rng seed");
+                debug = flag("debug").desc("enable debug commands");
+                rng_seed = opt_opt::<Seed, _>("SEED", "rng-seed")
+                    .desc("rng seed, either a number or a phrase (e.g. \"amber crypt owl\")");
+                daily = flag("daily")
+                    .desc("play the daily challenge, seeded from the date (UTC)");
+                generator = opt_opt::<GeneratorChoice, _>("NAME", "generator")
+                    .desc(format!(
+                        "level generation algorithm (one of {}, random)",
+                        GeneratorKind::ALL
+                            .iter()
+                            .map(|&kind| GeneratorChoice::Kind(kind).to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ))
+                    .with_default(GeneratorChoice::default());
+                generation_params = opt_opt::<PathBuf, _>("PATH", "generation-params")
+                    .desc("toml file containing level generation params");
+                generation_param_overrides = opt_multi::<String, _>("KEY=VALUE", "param")
+                    .desc("override a level generation param (e.g. water.noise_threshold=0.6)");
+                print_generation_params = flag("print-generation-params")
+                    .desc("print the level generation params in toml format and exit");
+                export_map = opt_opt::<PathBuf, _>("PATH", "export-map")
+                    .desc("export an image of the level to a png file and exit");
+                export_remembered = flag("export-remembered")
+                    .desc("only export the part of the level the player can see from the start");
+                map = opt_opt::<PathBuf, _>("PATH", "map")
+                    .desc("load the level from a text map file instead of generating it");
+                entity_templates = opt_opt::<PathBuf, _>("PATH", "entity-templates")
+                    .desc("file of entity templates to add to (or replace) the bundled ones");
+                world_width = opt_opt::<u32, _>("INT", "world-width")
+                    .desc("width of generated levels in cells")
+                    .with_default(app::WORLD_SIZE.width());
+                world_height = opt_opt::<u32, _>("INT", "world-height")
+                    .desc("height of generated levels in cells")
+                    .with_default(app::WORLD_SIZE.height());
+                edit = opt_opt::<PathBuf, _>("PATH", "edit")
+                    .desc("open a map file in the level editor, creating it when saved if necessary");
+                log_file = opt_opt::<PathBuf, _>("PATH", "log-file")
+                    .desc("file to write the log to, replacing its contents")
+                    .with_default_lazy(DEFAULT_LOG_FILE, || PathBuf::from(DEFAULT_LOG_FILE));
+                log_level = opt_opt::<LevelFilter, _>("LEVEL", "log-level")
+                    .desc("most verbose level to log (one of off, error, warn, info, debug, trace)")
+                    .with_default(LevelFilter::Info);
             } in {
                 Self {
                     terminal,
                     omniscient,
+                    debug,
                     rng_seed,
+                    daily,
+                    generator,
+                    generation_params,
+                    generation_param_overrides,
+                    print_generation_params,
+                    export_map,
+                    export_remembered,
+                    map,
+                    entity_templates,
+                    edit,
+                    world_width,
+                    world_height,
+                    log_file,
+                    log_level,
                 }
             }
         }
     }
 }
 
+// Fonts used to render the game, both in the WGPU context and when exporting images
+const FONT_BYTES_NORMAL: &[u8] = include_bytes!("./fonts/PxPlus_IBM_CGAthin.ttf");
+const FONT_BYTES_BOLD: &[u8] = include_bytes!("./fonts/PxPlus_IBM_CGA.ttf");
+const CELL_SIZE_PX: f64 = 16.;
+
+// The smallest world that can be generated
+const MIN_WORLD_SIZE: Size = Size::new_u16(20, 15);
+
 // Create a context for running chargrid apps in a WGPU graphical window
 fn wgpu_context() -> chargrid_wgpu::Context {
     use chargrid_wgpu::*;
-    const CELL_SIZE_PX: f64 = 16.;
     Context::new(Config {
         font_bytes: FontBytes {
-            normal: include_bytes!("./fonts/PxPlus_IBM_CGAthin.ttf").to_vec(),
-            bold: include_bytes!("./fonts/PxPlus_IBM_CGA.ttf").to_vec(),
+            normal: FONT_BYTES_NORMAL.to_vec(),
+            bold: FONT_BYTES_BOLD.to_vec(),
         },
         title: "Gridbugs Roguelike Tutorial".to_string(),
+        // Room for the default world size plus the side panel and message log
         window_dimensions_px: Dimensions {
-            width: 960.,
-            height: 720.,
+            width: 1280.,
+            height: 800.,
         },
         cell_dimensions_px: Dimensions {
             width: CELL_SIZE_PX,
@@ -54,23 +146,144 @@ fn wgpu_context() -> chargrid_wgpu::Context {
         },
         underline_width_cell_ratio: 0.1,
         underline_top_offset_cell_ratio: 0.8,
-        resizable: false,
+        resizable: true,
         force_secondary_adapter: false,
     })
 }
 
+// Read level generation params from a file (if any) and apply overrides from the command line
+fn load_generation_params(
+    path: Option<PathBuf>,
+    overrides: &[String],
+    world_size: Size,
+) -> Result<GenerationParams, String> {
+    let mut generation_params = match path {
+        Some(path) => GenerationParams::load(path)?,
+        None => GenerationParams::default(),
+    };
+    for param_override in overrides {
+        generation_params.apply_override(param_override)?;
+    }
+    generation_params.validate(world_size)?;
+    Ok(generation_params)
+}
+
 fn main() {
     use meap::Parser;
     let Args {
         terminal,
         omniscient,
+        debug,
         rng_seed,
+        daily,
+        generator,
+        generation_params,
+        generation_param_overrides,
+        print_generation_params,
+        export_map,
+        export_remembered,
+        map,
+        entity_templates,
+        edit,
+        world_width,
+        world_height,
+        log_file,
+        log_level,
     } = Args::parser().with_help_default().parse_env_or_exit();
+    if log_level != LevelFilter::Off {
+        if let Err(e) = logger::init(&log_file, log_level) {
+            eprintln!("Failed to start logging: {}", e);
+            std::process::exit(1);
+        }
+    }
+    // Templates must be installed before the first world is created
+    if let Some(path) = entity_templates {
+        if let Err(e) = game::EntityTemplates::load(path).and_then(game::EntityTemplates::install) {
+            eprintln!("Invalid entity templates: {}", e);
+            std::process::exit(1);
+        }
+    }
+    // The level generators need some room to place rooms and caves
+    if world_width < MIN_WORLD_SIZE.width() || world_height < MIN_WORLD_SIZE.height() {
+        eprintln!(
+            "World size must be at least {}x{}",
+            MIN_WORLD_SIZE.width(),
+            MIN_WORLD_SIZE.height()
+        );
+        std::process::exit(1);
+    }
+    let world_size = Size::new(world_width, world_height);
+    let generation_params =
+        match load_generation_params(generation_params, &generation_param_overrides, world_size) {
+            Ok(generation_params) => generation_params,
+            Err(e) => {
+                eprintln!("Invalid level generation params: {}", e);
+                std::process::exit(1);
+            }
+        };
+    if print_generation_params {
+        print!("{}", generation_params.to_toml_string());
+        return;
+    }
+    let map = match map.map(map_file::MapFile::load).transpose() {
+        Ok(map) => map,
+        Err(e) => {
+            eprintln!("Invalid map: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let rng_seed = match (rng_seed, daily) {
+        (Some(_), true) => {
+            eprintln!("Can't use --rng-seed with --daily");
+            std::process::exit(1);
+        }
+        (rng_seed, false) => rng_seed,
+        (None, true) => Some(Seed::daily()),
+    };
+    info!(
+        "Starting with world size {}x{}, generator {}, seed {:?}",
+        world_width, world_height, generator, rng_seed
+    );
     let config = game::Config {
         omniscient,
         rng_seed,
+        generator,
+        generation_params,
+        map,
+    };
+    if let Some(path) = export_map {
+        // Generate a level and write an image of it to a file without starting the game
+        let game = game::Game::new(world_size, config);
+        println!("Seed: {}", game.seed());
+        let mode = if export_remembered {
+            export::ExportMode::Remembered
+        } else {
+            export::ExportMode::WholeLevel
+        };
+        if let Err(e) = export::export_png(&game, mode, &path) {
+            eprintln!("Failed to export map to {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let app = match edit {
+        Some(path) => {
+            // Edit the map at the given path, starting with an empty map if it doesn't exist yet
+            let map = if path.exists() {
+                match map_file::MapFile::load(&path) {
+                    Ok(map) => map,
+                    Err(e) => {
+                        eprintln!("Invalid map: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            } else {
+                map_file::MapFile::new(world_size)
+            };
+            app::editor_app(map, path)
+        }
+        None => app::app(world_size, config, debug),
     };
-    let app = app::app(config);
     if terminal {
         // Run the app in an ANSI terminal chargrid context
         use chargrid_ansi_terminal::{Context, FromTermInfoRgb};
@@ -0,0 +1,247 @@
+use crate::{
+    game::{SpawnError, World},
+    rng::{RngStream, RngStreams},
+    terrain::Terrain,
+};
+use gridbugs::{
+    coord_2d::{Coord, Size},
+    grid_2d::Grid,
+};
+use log::warn;
+use std::{fs, path::Path};
+
+// A cell of a hand-made map
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapCell {
+    Wall,
+    CaveWall,
+    Door,
+    Floor,
+    CaveFloor,
+    Water,
+    Grass,
+}
+
+impl MapCell {
+    pub const ALL: &'static [Self] = &[
+        Self::Wall,
+        Self::CaveWall,
+        Self::Door,
+        Self::Floor,
+        Self::CaveFloor,
+        Self::Water,
+        Self::Grass,
+    ];
+
+    fn from_char(ch: char) -> Option<Self> {
+        Self::ALL.iter().find(|cell| cell.to_char() == ch).cloned()
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            Self::Wall => '#',
+            Self::CaveWall => '%',
+            Self::Door => '+',
+            Self::Floor => '.',
+            Self::CaveFloor => ',',
+            Self::Water => '~',
+            Self::Grass => '"',
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Wall => "wall",
+            Self::CaveWall => "cave wall",
+            Self::Door => "door",
+            Self::Floor => "floor",
+            Self::CaveFloor => "cave floor",
+            Self::Water => "water",
+            Self::Grass => "grass",
+        }
+    }
+
+    // The entity templates spawned to populate a cell of this kind, from the bottom layer up
+    fn template_names(self) -> &'static [&'static str] {
+        match self {
+            Self::Wall => &["wall"],
+            Self::CaveWall => &["cave_wall"],
+            Self::Door => &["door"],
+            Self::Floor => &["floor"],
+            Self::CaveFloor => &["cave_floor"],
+            Self::Water => &["water"],
+            Self::Grass => &["cave_floor", "grass"],
+        }
+    }
+
+    fn is_wall(self) -> bool {
+        matches!(self, Self::Wall | Self::CaveWall)
+    }
+}
+
+// The character marking the player's starting position. The player starts on a floor cell.
+const PLAYER_START_CHAR: char = '@';
+
+// A level loaded from a text file instead of being generated. Each character of the file is a
+// cell of the level:
+//
+// # wall
+// % cave wall
+// + door
+// . floor
+// , cave floor
+// ~ water
+// " grass (on cave floor)
+// @ player start (on floor)
+//
+// The map must be a rectangle with walls around its edge, containing exactly one player start.
+#[derive(Clone)]
+pub struct MapFile {
+    cells: Grid<MapCell>,
+    player_start: Coord,
+}
+
+impl MapFile {
+    // Creates a map of floor surrounded by walls, with the player starting in the centre
+    pub fn new(size: Size) -> Self {
+        let cells = Grid::new_fn(size, |coord| {
+            if size.is_on_edge(coord) {
+                MapCell::Wall
+            } else {
+                MapCell::Floor
+            }
+        });
+        Self {
+            cells,
+            player_start: (size / 2).to_coord().unwrap(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let rows = text
+            .lines()
+            .map(str::trim_end)
+            .filter(|row| !row.is_empty())
+            .map(|row| row.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let height = rows.len() as u32;
+        let width = rows.first().map(|row| row.len()).unwrap_or(0) as u32;
+        if width == 0 {
+            return Err("map is empty".to_string());
+        }
+        if rows.iter().any(|row| row.len() as u32 != width) {
+            return Err("all rows of the map must be the same length".to_string());
+        }
+        let mut player_start = None;
+        let mut cells = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &ch) in row.iter().enumerate() {
+                let cell = if ch == PLAYER_START_CHAR {
+                    let coord = Coord::new(x as i32, y as i32);
+                    if player_start.replace(coord).is_some() {
+                        return Err("map contains multiple player starts".to_string());
+                    }
+                    MapCell::Floor
+                } else {
+                    MapCell::from_char(ch)
+                        .ok_or_else(|| format!("unknown character {:?} at line {}", ch, y + 1))?
+                };
+                cells.push(cell);
+            }
+        }
+        let player_start = player_start.ok_or_else(|| "map has no player start".to_string())?;
+        let cells = Grid::new_fn(Size::new(width, height), |coord| {
+            cells[(coord.y as u32 * width + coord.x as u32) as usize]
+        });
+        let map_file = Self {
+            cells,
+            player_start,
+        };
+        map_file.validate()?;
+        Ok(map_file)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.cells.edge_iter().any(|cell| !cell.is_wall()) {
+            return Err("the edge of the map must be made of walls".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        Self::parse(&contents).map_err(|e| format!("failed to parse {}: {}", path.display(), e))
+    }
+
+    // Writes the map to a file in the format accepted by `MapFile::load`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        self.validate()?;
+        fs::write(path, self.to_text())
+            .map_err(|e| format!("failed to write {}: {}", path.display(), e))
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (coord, &cell) in self.cells.enumerate() {
+            if coord == self.player_start {
+                text.push(PLAYER_START_CHAR);
+            } else {
+                text.push(cell.to_char());
+            }
+            if coord.x == self.cells.width() as i32 - 1 {
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    pub fn size(&self) -> Size {
+        self.cells.size()
+    }
+
+    // Paints a single cell. The player always starts on the floor, so the player start can't be
+    // painted over.
+    pub fn set_cell(&mut self, coord: Coord, cell: MapCell) -> Result<(), String> {
+        if coord == self.player_start && cell != MapCell::Floor {
+            return Err(format!("can't paint {} over the player start", cell.name()));
+        }
+        let map_cell = self
+            .cells
+            .get_mut(coord)
+            .ok_or_else(|| format!("{:?} is outside the map", coord))?;
+        *map_cell = cell;
+        Ok(())
+    }
+
+    // Moves the player start, replacing the cell at the new start with floor
+    pub fn set_player_start(&mut self, coord: Coord) {
+        if let Some(map_cell) = self.cells.get_mut(coord) {
+            *map_cell = MapCell::Floor;
+            self.player_start = coord;
+        }
+    }
+
+    // Populate a world with the contents of the map
+    pub fn to_terrain(&self, rngs: &mut RngStreams) -> Result<Terrain, SpawnError> {
+        let rng = rngs.get(RngStream::Spawning);
+        let mut world = World::new(self.cells.size());
+        let player_entity = world.spawn_from_template("player", self.player_start, rng)?;
+        for (coord, &cell) in self.cells.enumerate() {
+            for template_name in cell.template_names() {
+                if let Err(e) = world.spawn_from_template(template_name, coord, rng) {
+                    warn!("Skipping {} at {:?}: {}", template_name, coord, e);
+                }
+            }
+        }
+        Ok(Terrain {
+            world,
+            player_entity,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test;
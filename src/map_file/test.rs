@@ -0,0 +1,102 @@
+use super::{MapCell, MapFile};
+use crate::{
+    game::{omniscient_visibility_grid, Tile, VisibleEntityData},
+    rng::RngStreams,
+};
+use gridbugs::coord_2d::{Coord, Size};
+
+fn tile(entity_data: &Option<VisibleEntityData>) -> Option<Tile> {
+    entity_data.as_ref().map(|entity_data| entity_data.tile)
+}
+
+const SMALL_MAP: &str = "\
+#####
+#@.~#
+#,\"%#
+#####
+";
+
+#[test]
+fn map_is_parsed() {
+    let map = MapFile::parse(SMALL_MAP).unwrap();
+    assert_eq!(map.size(), Size::new(5, 4));
+    assert_eq!(map.player_start, Coord::new(1, 1));
+    // The player starts on floor
+    assert_eq!(*map.cells.get_checked(Coord::new(1, 1)), MapCell::Floor);
+    assert_eq!(*map.cells.get_checked(Coord::new(3, 1)), MapCell::Water);
+    assert_eq!(*map.cells.get_checked(Coord::new(2, 2)), MapCell::Grass);
+    assert_eq!(*map.cells.get_checked(Coord::new(3, 2)), MapCell::CaveWall);
+}
+
+#[test]
+fn map_round_trips_through_text() {
+    let map = MapFile::parse(SMALL_MAP).unwrap();
+    assert_eq!(map.to_text(), SMALL_MAP);
+}
+
+#[test]
+fn maps_with_ragged_rows_are_rejected() {
+    let e = MapFile::parse("####\n#@.#\n###\n").err().unwrap();
+    assert!(e.contains("same length"), "{}", e);
+}
+
+#[test]
+fn maps_without_exactly_one_player_start_are_rejected() {
+    let e = MapFile::parse("####\n#..#\n####\n").err().unwrap();
+    assert!(e.contains("no player start"), "{}", e);
+    let e = MapFile::parse("####\n#@@#\n####\n").err().unwrap();
+    assert!(e.contains("multiple player starts"), "{}", e);
+}
+
+#[test]
+fn maps_with_unknown_characters_are_rejected() {
+    let e = MapFile::parse("####\n#@?#\n####\n").err().unwrap();
+    assert!(e.contains("unknown character '?' at line 2"), "{}", e);
+}
+
+#[test]
+fn maps_without_walls_around_the_edge_are_rejected() {
+    assert!(MapFile::parse("####\n#@..\n####\n").is_err());
+    assert!(MapFile::parse("").is_err());
+}
+
+#[test]
+fn map_round_trips_through_terrain() {
+    let map = MapFile::parse(SMALL_MAP).unwrap();
+    let terrain = map.to_terrain(&mut RngStreams::new(0)).unwrap();
+    let visibility_grid = omniscient_visibility_grid(&terrain.world);
+    let tiles_at = |x, y| {
+        let data = visibility_grid.get_data(Coord::new(x, y)).unwrap();
+        (
+            tile(&data.entity_data.floor),
+            tile(&data.entity_data.feature),
+            tile(&data.entity_data.character),
+        )
+    };
+    assert!(matches!(tiles_at(0, 0), (None, Some(Tile::Wall), None)));
+    assert!(matches!(
+        tiles_at(1, 1),
+        (Some(Tile::Floor), None, Some(Tile::Player))
+    ));
+    assert!(matches!(tiles_at(2, 1), (Some(Tile::Floor), None, None)));
+    assert!(matches!(tiles_at(3, 1), (Some(Tile::Water), None, None)));
+    assert!(matches!(
+        tiles_at(1, 2),
+        (Some(Tile::CaveFloor), None, None)
+    ));
+    assert!(matches!(
+        tiles_at(2, 2),
+        (Some(Tile::CaveFloor), Some(Tile::Grass), None)
+    ));
+    assert!(matches!(tiles_at(3, 2), (None, Some(Tile::CaveWall), None)));
+}
+
+#[test]
+fn player_start_cannot_be_painted_over() {
+    let mut map = MapFile::parse(SMALL_MAP).unwrap();
+    assert!(map.set_cell(Coord::new(1, 1), MapCell::Wall).is_err());
+    assert_eq!(*map.cells.get_checked(Coord::new(1, 1)), MapCell::Floor);
+    map.set_cell(Coord::new(2, 1), MapCell::Wall).unwrap();
+    assert_eq!(*map.cells.get_checked(Coord::new(2, 1)), MapCell::Wall);
+    assert!(map.set_cell(Coord::new(5, 1), MapCell::Wall).is_err());
+}
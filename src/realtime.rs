@@ -15,7 +15,7 @@ impl<'a> ContextContainsRealtimeComponents for RealtimeContext<'a> {
     fn components_mut(&mut self) -> &mut Self::Components {
         self.world.realtime_components_mut()
     }
-    fn realtime_entities(&self) -> Entities {
+    fn realtime_entities(&self) -> Entities<'_> {
         self.world.realtime_entities()
     }
 }
@@ -43,6 +43,14 @@ mod water_animation {
                 rng: Isaac64Rng::from_rng(rng).unwrap(),
             }
         }
+
+        // The colour of water before its animation first changes it
+        pub fn initial_colour_hint<R: Rng>(rng: &mut R) -> ColourHint {
+            ColourHint {
+                foreground: choose_colour(rng),
+                background: choose_colour(rng),
+            }
+        }
     }
 
     pub struct UpdateColourHint;
@@ -62,38 +70,104 @@ mod water_animation {
     const WATER_COLOUR_DIM: Rgb24 = Rgb24::new(0, 31, 63);
     const WATER_COLOUR_BRIGHT: Rgb24 = Rgb24::new(0, 63, 127);
 
+    fn choose_colour<R: Rng>(rng: &mut R) -> Rgb24 {
+        rng.gen_range(WATER_COLOUR_DIM..WATER_COLOUR_BRIGHT)
+    }
+
     impl<'a> RealtimeComponentApplyEvent<RealtimeContext<'a>> for State {
         fn apply_event(_: UpdateColourHint, entity: Entity, context: &mut RealtimeContext<'a>) {
             let colour_hint_component = &mut context.world.components_mut().colour_hint;
-            let mut choose_colour = || context.rng.gen_range(WATER_COLOUR_DIM..WATER_COLOUR_BRIGHT);
             if let Some(&(mut colour_hint)) = colour_hint_component.get(entity) {
-                let new_colour = choose_colour();
+                let new_colour = choose_colour(context.rng);
                 if context.rng.gen() {
                     colour_hint.foreground = new_colour;
                 } else {
                     colour_hint.background = new_colour;
                 }
                 colour_hint_component.insert(entity, colour_hint);
-            } else {
-                colour_hint_component.insert(
-                    entity,
-                    ColourHint {
-                        foreground: choose_colour(),
-                        background: choose_colour(),
-                    },
-                );
+            }
+        }
+    }
+}
+
+mod projectile {
+    use super::RealtimeContext;
+    use gridbugs::{
+        coord_2d::Coord,
+        entity_table::Entity,
+        entity_table_realtime::{RealtimeComponent, RealtimeComponentApplyEvent},
+    };
+    use std::time::Duration;
+
+    // How long a projectile takes to move between adjacent cells
+    const STEP_DURATION: Duration = Duration::from_millis(30);
+
+    // Moves a projectile along a path, one cell at a time
+    #[derive(Clone, Debug)]
+    pub struct State {
+        path: Vec<Coord>,
+        next_index: usize,
+    }
+
+    impl State {
+        pub fn new(path: Vec<Coord>) -> Self {
+            Self {
+                path,
+                next_index: 0,
+            }
+        }
+
+        pub fn cells_remaining(&self) -> usize {
+            self.path.len() - self.next_index
+        }
+    }
+
+    pub enum ProjectileEvent {
+        MoveTo(Coord),
+        // The projectile has reached the end of its path
+        Land,
+    }
+
+    impl RealtimeComponent for State {
+        type Event = ProjectileEvent;
+
+        fn tick(&mut self) -> (Self::Event, Duration) {
+            match self.path.get(self.next_index) {
+                Some(&coord) => {
+                    self.next_index += 1;
+                    (ProjectileEvent::MoveTo(coord), STEP_DURATION)
+                }
+                None => (ProjectileEvent::Land, STEP_DURATION),
+            }
+        }
+    }
+
+    impl<'a> RealtimeComponentApplyEvent<RealtimeContext<'a>> for State {
+        fn apply_event(event: ProjectileEvent, entity: Entity, context: &mut RealtimeContext<'a>) {
+            match event {
+                ProjectileEvent::MoveTo(coord) => context.world.move_entity(entity, coord),
+                // The game notices the animation has finished and resolves what was hit
+                ProjectileEvent::Land => {
+                    context
+                        .world
+                        .realtime_components_mut()
+                        .projectile
+                        .remove(entity);
+                }
             }
         }
     }
 }
 
 pub mod types {
+    pub use super::projectile::State as ProjectileState;
     pub use super::water_animation::State as WaterAnimationState;
 }
 
 declare_realtime_entity_module! {
     components<'a>[RealtimeContext<'a>] {
         water_animation: types::WaterAnimationState,
+        projectile: types::ProjectileState,
     }
 }
 
@@ -0,0 +1,97 @@
+use rand::SeedableRng;
+use rand_isaac::Isaac64Rng;
+
+// Hash bytes with FNV-1a. This is used rather than the standard library's hasher because its
+// output may change between versions of Rust, which would change the level generated from each
+// seed.
+pub fn stable_hash<I: IntoIterator<Item = u8>>(bytes: I) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+    bytes.into_iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+// A part of the game which makes random choices. Each stream has its own rng so that changing how
+// many random numbers one part of the game uses doesn't change the choices made by other parts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RngStream {
+    // The shape of the level: rooms, corridors, caves and vaults
+    Layout,
+    // Which door candidates become doors
+    Doors,
+    Water,
+    Grass,
+    // Where monsters are placed
+    Monsters,
+    // Grass regrowing and spreading, and water rising and falling
+    Environment,
+    Ai,
+    // Entities placed while building a level, and projectiles fired during play
+    Spawning,
+    Animation,
+    // The seed of the level after the current one
+    NextLevel,
+}
+
+impl RngStream {
+    pub const ALL: &'static [Self] = &[
+        Self::Layout,
+        Self::Doors,
+        Self::Water,
+        Self::Grass,
+        Self::Monsters,
+        Self::Environment,
+        Self::Ai,
+        Self::Spawning,
+        Self::Animation,
+        Self::NextLevel,
+    ];
+
+    // The name is mixed into the stream's seed, so changing it will change the stream
+    fn name(self) -> &'static str {
+        match self {
+            Self::Layout => "layout",
+            Self::Doors => "doors",
+            Self::Water => "water",
+            Self::Grass => "grass",
+            Self::Monsters => "monsters",
+            Self::Environment => "environment",
+            Self::Ai => "ai",
+            Self::Spawning => "spawning",
+            Self::Animation => "animation",
+            Self::NextLevel => "next-level",
+        }
+    }
+
+    // Combine a seed with the name of the stream
+    fn seed(self, seed: u64) -> u64 {
+        stable_hash(seed.to_le_bytes().into_iter().chain(self.name().bytes()))
+    }
+}
+
+// An independent rng for each `RngStream`, all derived from a single seed
+pub struct RngStreams {
+    rngs: Vec<(RngStream, Isaac64Rng)>,
+}
+
+impl RngStreams {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rngs: RngStream::ALL
+                .iter()
+                .map(|&stream| (stream, Isaac64Rng::seed_from_u64(stream.seed(seed))))
+                .collect(),
+        }
+    }
+
+    pub fn get(&mut self, stream: RngStream) -> &mut Isaac64Rng {
+        self.rngs
+            .iter_mut()
+            .find_map(|(s, rng)| (*s == stream).then_some(rng))
+            .unwrap_or_else(|| panic!("no rng for stream {:?}", stream))
+    }
+}
+
+#[cfg(test)]
+mod test;
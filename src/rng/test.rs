@@ -0,0 +1,43 @@
+use super::{stable_hash, RngStream, RngStreams};
+use rand::Rng;
+
+#[test]
+fn stable_hash_matches_fnv_1a() {
+    assert_eq!(stable_hash([]), 0xcbf29ce484222325);
+    assert_eq!(stable_hash(*b"a"), 0xaf63dc4c8601ec8c);
+    assert_eq!(stable_hash(*b"foobar"), 0x85944171f73967e8);
+}
+
+#[test]
+fn stream_seeds_are_stable() {
+    // Changing these values changes the level generated from every seed
+    let expected = [
+        (RngStream::Layout, 14033962360913505045),
+        (RngStream::Doors, 3558138355574058972),
+        (RngStream::Water, 3468995103525521950),
+        (RngStream::Grass, 9928877735314593067),
+        (RngStream::Monsters, 9542448950901250236),
+        (RngStream::Environment, 17716697549005987416),
+        (RngStream::Ai, 5357142252692678777),
+        (RngStream::Spawning, 16226022554884554870),
+        (RngStream::Animation, 56881274231324807),
+        (RngStream::NextLevel, 13674834192358057105),
+    ];
+    assert_eq!(RngStream::ALL.len(), expected.len());
+    for (stream, seed) in expected {
+        assert_eq!(stream.seed(42), seed, "{:?}", stream);
+    }
+}
+
+#[test]
+fn each_stream_has_its_own_rng() {
+    let mut rngs = RngStreams::new(42);
+    let values = RngStream::ALL
+        .iter()
+        .map(|&stream| rngs.get(stream).gen::<u64>())
+        .collect::<Vec<_>>();
+    let mut fresh = RngStreams::new(42);
+    for (&stream, &value) in RngStream::ALL.iter().zip(values.iter()).rev() {
+        assert_eq!(fresh.get(stream).gen::<u64>(), value, "{:?}", stream);
+    }
+}
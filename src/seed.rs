@@ -0,0 +1,124 @@
+use crate::rng::stable_hash;
+use rand::{seq::SliceRandom, Rng};
+use std::{
+    fmt,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// Words used to make up random seed phrases. They are kept short so phrases fit in the side panel.
+const WORDS: &[&str] = &[
+    "adder", "amber", "anvil", "ash", "axe", "bat", "birch", "bog", "bone", "brass", "briar",
+    "cairn", "cave", "chain", "chalk", "clay", "cliff", "cloak", "coal", "cog", "crow", "crown",
+    "crypt", "dawn", "deep", "dirge", "dusk", "dust", "echo", "eel", "elm", "ember", "fern",
+    "flask", "flint", "fog", "forge", "fox", "frost", "fungi", "gate", "ghost", "glade", "glass",
+    "gloom", "gnome", "grave", "grub", "hall", "hare", "hawk", "heron", "hilt", "horn", "husk",
+    "imp", "iron", "ivory", "ivy", "jade", "jar", "keep", "kelp", "knot", "lark", "ledge", "leech",
+    "lily", "loam", "lock", "lute", "marsh", "mask", "mire", "mist", "mole", "moon", "moss",
+    "moth", "newt", "night", "oak", "onyx", "orb", "ore", "owl", "pearl", "peat", "pike", "pine",
+    "pit", "plume", "pool", "quill", "rat", "raven", "reed", "relic", "ridge", "river", "root",
+    "rope", "ruin", "rune", "rust", "salt", "scale", "scree", "shade", "shale", "shell", "sigil",
+    "skull", "slate", "slug", "smoke", "snake", "spire", "spore", "stag", "stair", "stone",
+    "storm", "sun", "swamp", "thorn", "tide", "toad",
+];
+
+// The number of words in a random seed phrase
+const NUM_PHRASE_WORDS: usize = 3;
+
+// The seed a level is generated from. Seeds are usually short phrases so they are easy to read
+// out and type in, but any number can be used as a seed too.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Seed {
+    Number(u64),
+    // Lowercase words separated by hyphens
+    Phrase(String),
+}
+
+impl Seed {
+    // Choose a random phrase made of words from the built-in word list
+    pub fn random_phrase<R: Rng>(rng: &mut R) -> Self {
+        let words = (0..NUM_PHRASE_WORDS)
+            .map(|_| *WORDS.choose(rng).unwrap())
+            .collect::<Vec<_>>();
+        Self::Phrase(words.join("-"))
+    }
+
+    // The seed of the daily challenge for the current date (in UTC), which is the same for
+    // everyone playing on that day
+    pub fn daily() -> Self {
+        let days_since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time is before the unix epoch")
+            .as_secs()
+            / (60 * 60 * 24);
+        Self::daily_for_day(days_since_epoch as i64)
+    }
+
+    // The seed of the daily challenge for the given number of days since 1970-01-01
+    fn daily_for_day(days_since_epoch: i64) -> Self {
+        let (year, month, day) = civil_from_days(days_since_epoch);
+        Self::Phrase(format!("daily-{}-{:02}-{:02}", year, month, day))
+    }
+
+    // The number used to seed the random number generators
+    pub fn value(&self) -> u64 {
+        match self {
+            Self::Number(value) => *value,
+            Self::Phrase(phrase) => stable_hash(phrase.bytes()),
+        }
+    }
+}
+
+// Convert a number of days since 1970-01-01 into a (year, month, day) date. Based on Howard
+// Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // Months are counted from March so the leap day is at the end of the year
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+impl fmt::Display for Seed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Number(value) => write!(f, "{}", value),
+            Self::Phrase(phrase) => write!(f, "{}", phrase),
+        }
+    }
+}
+
+// Seeds made only of digits are numbers. Anything else is a phrase, which is normalized so that
+// differences in case and in how words are separated don't change the seed.
+impl FromStr for Seed {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(value) = s.parse() {
+            return Ok(Self::Number(value));
+        }
+        let words = s
+            .split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+        if words.is_empty() {
+            return Err("seed is empty".to_string());
+        }
+        Ok(Self::Phrase(words.join("-")))
+    }
+}
+
+#[cfg(test)]
+mod test;
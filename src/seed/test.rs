@@ -0,0 +1,72 @@
+use super::{civil_from_days, Seed};
+
+#[test]
+fn days_are_converted_to_dates() {
+    assert_eq!(civil_from_days(0), (1970, 1, 1));
+    assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    assert_eq!(civil_from_days(11016), (2000, 2, 29));
+    assert_eq!(civil_from_days(11017), (2000, 3, 1));
+    assert_eq!(civil_from_days(20744), (2026, 10, 18));
+}
+
+#[test]
+fn daily_seeds_are_named_after_the_date() {
+    assert_eq!(
+        Seed::daily_for_day(0),
+        Seed::Phrase("daily-1970-01-01".to_string())
+    );
+    assert_eq!(
+        Seed::daily_for_day(20744),
+        Seed::Phrase("daily-2026-10-18".to_string())
+    );
+    // The daily seed parses back to itself, so it can be shared with --rng-seed
+    let seed = Seed::daily();
+    assert_eq!(seed.to_string().parse::<Seed>(), Ok(seed));
+}
+
+#[test]
+fn numbers_are_parsed_as_numbers() {
+    assert_eq!("42".parse::<Seed>(), Ok(Seed::Number(42)));
+    assert_eq!(" 42\n".parse::<Seed>(), Ok(Seed::Number(42)));
+    assert_eq!(
+        "18446744073709551615".parse::<Seed>(),
+        Ok(Seed::Number(u64::MAX))
+    );
+    // Too big to be a number, so it's a phrase
+    assert_eq!(
+        "18446744073709551616".parse::<Seed>(),
+        Ok(Seed::Phrase("18446744073709551616".to_string()))
+    );
+    assert_eq!("-1".parse::<Seed>(), Ok(Seed::Phrase("1".to_string())));
+}
+
+#[test]
+fn phrases_are_normalized() {
+    let expected = Ok(Seed::Phrase("amber-crypt-owl".to_string()));
+    for text in [
+        "amber-crypt-owl",
+        "amber crypt owl",
+        "  Amber   CRYPT\towl ",
+        "amber_crypt--owl",
+        "-amber-crypt-owl-",
+    ] {
+        assert_eq!(text.parse::<Seed>(), expected, "{:?}", text);
+    }
+    assert_eq!(
+        "Amber Crypt Owl".parse::<Seed>().unwrap().value(),
+        "amber-crypt-owl".parse::<Seed>().unwrap().value()
+    );
+}
+
+#[test]
+fn empty_seeds_are_rejected() {
+    assert!("".parse::<Seed>().is_err());
+    assert!(" - _ ".parse::<Seed>().is_err());
+}
+
+#[test]
+fn seeds_round_trip_through_text() {
+    for seed in [Seed::Number(7), Seed::Phrase("moss-rune-toad".to_string())] {
+        assert_eq!(seed.to_string().parse::<Seed>(), Ok(seed));
+    }
+}
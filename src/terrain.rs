@@ -1,4 +1,7 @@
-use crate::game::World;
+use crate::{
+    game::{SpawnError, World},
+    rng::{RngStream, RngStreams},
+};
 use gridbugs::{
     coord_2d::{Axis, Coord, Size},
     direction::{CardinalDirection, Direction},
@@ -6,8 +9,19 @@ use gridbugs::{
     grid_2d::Grid,
     perlin2::Perlin2,
 };
+use log::{info, warn};
 use rand::{seq::SliceRandom, Rng};
-use std::{collections::HashSet, mem};
+use std::{collections::HashSet, fmt, mem, str::FromStr};
+
+mod bsp;
+mod cellular_automata_cave;
+mod drunkards_walk;
+mod params;
+mod vault;
+
+pub use params::GenerationParams;
+use params::{CaveParams, GameOfLifeParams, GrassParams, MonsterParams, RoomParams, WaterParams};
+use vault::{Vault, VaultCell};
 
 // Will be used as cells in grids representing simple maps of levels during terrain generation
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -60,6 +74,25 @@ impl Rect {
     fn centre(&self) -> Coord {
         self.top_left + (self.size / 2)
     }
+
+    // Returns true iff any coordinate of the straight line between `a` and `b` is inside the
+    // rectangle. The line must be aligned with an axis.
+    fn intersects_line(&self, a: Coord, b: Coord) -> bool {
+        let right = self.top_left.x + self.size.width() as i32;
+        let bottom = self.top_left.y + self.size.height() as i32;
+        a.x.min(b.x) < right
+            && a.x.max(b.x) >= self.top_left.x
+            && a.y.min(b.y) < bottom
+            && a.y.max(b.y) >= self.top_left.y
+    }
+
+    // Returns true iff the two rectangles share any coordinates
+    fn overlaps(&self, other: &Rect) -> bool {
+        self.top_left.x < other.top_left.x + other.size.width() as i32
+            && other.top_left.x < self.top_left.x + self.size.width() as i32
+            && self.top_left.y < other.top_left.y + other.size.height() as i32
+            && other.top_left.y < self.top_left.y + self.size.height() as i32
+    }
 }
 
 // Represents a room during terrain generation
@@ -147,17 +180,40 @@ fn l_shaped_corridor_with_first_axis(
     ret
 }
 
-// Returns a vec of coordinates that define an L-shaped corridor from start to end (in order). The
-// corridor stops if it encounters a cell adjacent to a floor cell according to the given map. The
-// first axis that is traversed in the L-shaped corridor is chosen at random.
-fn l_shaped_corridor<R: Rng>(
-    start: Coord,
-    end: Coord,
-    map: &Grid<FloorOrWall>,
-    rng: &mut R,
-) -> Vec<Coord> {
-    let axis = if rng.gen() { Axis::X } else { Axis::Y };
-    l_shaped_corridor_with_first_axis(start, end, map, axis)
+// Returns true iff an L-shaped corridor from start to end which first traverses the given axis
+// would pass through the given rectangle
+fn l_shaped_corridor_intersects(start: Coord, end: Coord, first_axis: Axis, rect: &Rect) -> bool {
+    let corner = Coord::new_axis(
+        end.get(first_axis),
+        start.get(first_axis.other()),
+        first_axis,
+    );
+    rect.intersects_line(start, corner) || rect.intersects_line(corner, end)
+}
+
+// A vault which has been placed in a level
+#[derive(Clone)]
+struct PlacedVault {
+    rect: Rect,
+    vault: Vault,
+}
+
+impl PlacedVault {
+    // Returns an iterator over the cells of the vault, along with their coordinates in the level
+    fn cells(&self) -> impl '_ + Iterator<Item = (Coord, &VaultCell)> {
+        self.vault
+            .cells
+            .enumerate()
+            .map(|(coord, cell)| (coord + self.rect.top_left, cell))
+    }
+
+    // Returns an iterator over the coordinates of the doors in the edge of the vault, along with
+    // the direction leading out of the vault from each door
+    fn doors(&self) -> impl '_ + Iterator<Item = (Coord, CardinalDirection)> {
+        self.vault
+            .doors()
+            .map(|(coord, direction)| (coord + self.rect.top_left, direction))
+    }
 }
 
 // Data structure representing the state of the room-placement algorithm
@@ -170,6 +226,8 @@ struct RoomPlacement {
     door_candidates: Vec<Coord>,
     // Tracks whether there is a wall or floor at each location
     map: Grid<FloorOrWall>,
+    // Vaults that have been placed. Corridors never pass through vaults.
+    vaults: Vec<PlacedVault>,
 }
 
 impl RoomPlacement {
@@ -179,51 +237,165 @@ impl RoomPlacement {
             edge_coords: HashSet::new(),
             door_candidates: Vec::new(),
             map: Grid::new_copy(size, FloorOrWall::Wall),
+            vaults: Vec::new(),
+        }
+    }
+
+    // Returns true iff the given rectangle overlaps with any placed vault
+    fn overlaps_vault(&self, rect: &Rect) -> bool {
+        self.vaults.iter().any(|vault| vault.rect.overlaps(rect))
+    }
+
+    // Randomly choose which axis an L-shaped corridor from start to end will traverse first, such
+    // that the corridor doesn't pass through any vaults or the given rectangle. Returns `None` if
+    // there is no such axis.
+    fn choose_corridor_axis<R: Rng>(
+        &self,
+        start: Coord,
+        end: Coord,
+        avoid: Option<&Rect>,
+        rng: &mut R,
+    ) -> Option<Axis> {
+        let axis = if rng.gen() { Axis::X } else { Axis::Y };
+        [axis, axis.other()].into_iter().find(|&axis| {
+            self.vaults
+                .iter()
+                .map(|vault| &vault.rect)
+                .chain(avoid)
+                .all(|rect| !l_shaped_corridor_intersects(start, end, axis, rect))
+        })
+    }
+
+    // Carves a corridor out of the map, recording any cells along it which would be suitable for
+    // doors
+    fn dig_corridor(&mut self, corridor: &[Coord]) {
+        // Carve out the corridor from the map
+        for &coord in corridor {
+            *self.map.get_checked_mut(coord) = FloorOrWall::Floor;
+        }
+        // Update the list of door candidates along this corridor
+        let mut door_candidate = None;
+        for &coord in corridor {
+            if self.edge_coords.contains(&coord) && is_cell_in_corridor(&self.map, coord) {
+                door_candidate = Some(coord);
+            } else if let Some(coord) = door_candidate.take() {
+                // The candidate is stored in door_candidate (an Option<Coord>) until a
+                // non-candidate cell is found, at which point the currently-stored candidate
+                // is added to the list of door candidates. This prevents multiple consecutive
+                // door candidates being added, which could result in several doors in a row
+                // which is undesired.
+                self.door_candidates.push(coord);
+            }
+        }
+        if let Some(coord) = door_candidate {
+            self.door_candidates.push(coord);
         }
     }
 
     // Adds a new room unless it overlaps with the floor
     fn try_add_room<R: Rng>(&mut self, new_room: Room, rng: &mut R) {
-        // Don't add the room if it overlaps with the floor
-        if new_room.overlaps_with_floor(&self.map) {
+        // Don't add the room if it overlaps with the floor or a vault
+        if new_room.overlaps_with_floor(&self.map) || self.overlaps_vault(&new_room.rect) {
+            return;
+        }
+        // Randomly choose two rooms to connect the new room to
+        let existing_rooms = self
+            .rooms
+            .choose_multiple(rng, 2)
+            .cloned()
+            .collect::<Vec<_>>();
+        self.add_room_connected_to(new_room, &existing_rooms, rng);
+    }
+
+    // Adds a new room, connecting it to each of the given existing rooms with corridors. Corridors
+    // which would pass through a vault are skipped, and if no corridors can be added the room is
+    // not added at all.
+    fn add_room_connected_to<R: Rng>(
+        &mut self,
+        new_room: Room,
+        existing_rooms: &[Room],
+        rng: &mut R,
+    ) {
+        let start = new_room.rect.centre();
+        let corridor_ends = existing_rooms
+            .iter()
+            .filter_map(|existing_room| {
+                let end = existing_room.rect.centre();
+                self.choose_corridor_axis(start, end, None, rng)
+                    .map(|axis| (end, axis))
+            })
+            .collect::<Vec<_>>();
+        if corridor_ends.is_empty() && !existing_rooms.is_empty() {
             return;
         }
         // Add the room's wall to the collection of edge coords
         self.edge_coords.extend(new_room.rect.edge_coords());
-        // Randomly choose two rooms to connect the new room to
-        for &existing_room in self.rooms.choose_multiple(rng, 2) {
+        for (end, axis) in corridor_ends {
             // List the coordinates of an L-shaped corridor between the centres of the new room and
             // the chosen exsiting room
-            let corridor = l_shaped_corridor(
-                new_room.rect.centre(),
-                existing_room.rect.centre(),
-                &self.map,
-                rng,
-            );
-            // Carve out the corridor from the map
-            for &coord in &corridor {
-                *self.map.get_checked_mut(coord) = FloorOrWall::Floor;
+            let corridor = l_shaped_corridor_with_first_axis(start, end, &self.map, axis);
+            self.dig_corridor(&corridor);
+        }
+        new_room.add_floor_to_map(&mut self.map);
+        self.rooms.push(new_room);
+    }
+
+    // Adds a vault at a random position unless it overlaps with the floor or another vault, or
+    // none of its doors can be connected to an existing room with a corridor. There must be at
+    // least one room already.
+    fn try_add_vault<R: Rng>(&mut self, vault: Vault, rng: &mut R) {
+        let bounds = self.map.size();
+        let size = vault.size();
+        // Vaults don't touch the edge of the map, so every door has a cell on either side of it
+        if size.width() + 2 > bounds.width() || size.height() + 2 > bounds.height() {
+            return;
+        }
+        let top_left_bounds = bounds - size;
+        let rect = Rect {
+            top_left: Coord::new(
+                rng.gen_range(1..top_left_bounds.width()) as i32,
+                rng.gen_range(1..top_left_bounds.height()) as i32,
+            ),
+            size,
+        };
+        if rect
+            .coords()
+            .any(|coord| *self.map.get_checked(coord) == FloorOrWall::Floor)
+            || self.overlaps_vault(&rect)
+        {
+            return;
+        }
+        let placed_vault = PlacedVault { rect, vault };
+        // Connect the cell outside each door to a randomly-chosen room
+        let mut corridor_ends = Vec::new();
+        for (door, direction) in placed_vault.doors() {
+            let outside = door + direction.coord();
+            if bounds.is_on_edge(outside) {
+                continue;
             }
-            // Update the list of door candidates along this corridor
-            let mut door_candidate = None;
-            for &coord in &corridor {
-                if self.edge_coords.contains(&coord) && is_cell_in_corridor(&self.map, coord) {
-                    door_candidate = Some(coord);
-                } else if let Some(coord) = door_candidate.take() {
-                    // The candidate is stored in door_candidate (an Option<Coord>) until a
-                    // non-candidate cell is found, at which point the currently-stored candidate
-                    // is added to the list of door candidates. This prevents multiple consecutive
-                    // door candidates being added, which could result in several doors in a row
-                    // which is undesired.
-                    self.door_candidates.push(coord);
-                }
+            let end = self.rooms.choose(rng).unwrap().rect.centre();
+            if let Some(axis) = self.choose_corridor_axis(outside, end, Some(&rect), rng) {
+                corridor_ends.push((outside, end, axis));
             }
-            if let Some(coord) = door_candidate {
-                self.door_candidates.push(coord);
+        }
+        if corridor_ends.is_empty() {
+            return;
+        }
+        // Corridors are dug before the vault's floor is added to the map, otherwise they would
+        // stop as soon as they passed one of the vault's other doors
+        for (outside, end, axis) in corridor_ends {
+            let mut corridor = vec![outside];
+            corridor.extend(l_shaped_corridor_with_first_axis(
+                outside, end, &self.map, axis,
+            ));
+            self.dig_corridor(&corridor);
+        }
+        for (coord, cell) in placed_vault.cells() {
+            if !cell.level_cell.is_wall() {
+                *self.map.get_checked_mut(coord) = FloorOrWall::Floor;
             }
         }
-        new_room.add_floor_to_map(&mut self.map);
-        self.rooms.push(new_room);
+        self.vaults.push(placed_vault);
     }
 }
 
@@ -241,48 +413,75 @@ struct RoomsAndCorridorsLevel {
     map: Grid<RoomsAndCorridorsCell>,
     // Location where the player will start
     player_spawn: Coord,
+    // Vaults placed in the level, which must be stamped onto the final map
+    vaults: Vec<PlacedVault>,
 }
 
 impl RoomsAndCorridorsLevel {
     // Randomly generates a level made up of rooms and corridors
-    fn generate<R: Rng>(size: Size, rng: &mut R) -> Self {
-        const NUM_ROOM_ATTEMPTS: usize = 50;
-        const MIN_ROOM_SIZE: Size = Size::new_u16(5, 5);
-        const MAX_ROOM_SIZE: Size = Size::new_u16(11, 9);
+    fn generate(size: Size, params: &RoomParams, rngs: &mut RngStreams) -> Self {
+        let rng = rngs.get(RngStream::Layout);
         let mut room_placement = RoomPlacement::new(size);
+        let vaults = vault::bundled_vaults();
         // Add all the rooms and corridors
-        for _ in 0..NUM_ROOM_ATTEMPTS {
+        for _ in 0..params.num_room_attempts {
+            // Vaults are only placed once there is a room to connect them to
+            if !room_placement.rooms.is_empty()
+                && params.vault_chance_percent > 0
+                && rng.gen_range(0..100) < params.vault_chance_percent
+            {
+                let vault = vaults.choose(rng).unwrap().random_transform(rng);
+                room_placement.try_add_vault(vault, rng);
+                continue;
+            }
             let new_room = Room {
-                rect: Rect::choose(size, MIN_ROOM_SIZE, MAX_ROOM_SIZE, rng),
+                rect: Rect::choose(
+                    size,
+                    params.min_room_size.to_size(),
+                    params.max_room_size.to_size(),
+                    rng,
+                ),
             };
             room_placement.try_add_room(new_room, rng);
         }
+        Self::from_room_placement(room_placement, rngs)
+    }
+
+    // Creates a level from the result of the room-placement algorithm, randomly adding doors
+    fn from_room_placement(room_placement: RoomPlacement, rngs: &mut RngStreams) -> Self {
         // Create the map made of `RoomsAndCorridorsCell`s
         let mut map = Grid::new_grid_map(room_placement.map, |floor_or_wall| match floor_or_wall {
             FloorOrWall::Floor => RoomsAndCorridorsCell::Floor,
             FloorOrWall::Wall => RoomsAndCorridorsCell::Wall,
         });
+        // Add the doors of vaults
+        for vault in &room_placement.vaults {
+            for (coord, _) in vault.doors() {
+                *map.get_checked_mut(coord) = RoomsAndCorridorsCell::Door;
+            }
+        }
         // Add doors
         for door_candidate_coord in room_placement.door_candidates {
             // Each door candidate has a 50% chance to become a door
-            if rng.gen::<bool>() {
+            if rngs.get(RngStream::Doors).gen::<bool>() {
                 *map.get_checked_mut(door_candidate_coord) = RoomsAndCorridorsCell::Door;
             }
         }
         // The player will start in the centre of a randomly-chosen room
-        let player_spawn = room_placement.rooms.choose(rng).unwrap().rect.centre();
-        Self { map, player_spawn }
+        let player_spawn = room_placement
+            .rooms
+            .choose(rngs.get(RngStream::Layout))
+            .unwrap()
+            .rect
+            .centre();
+        Self {
+            map,
+            player_spawn,
+            vaults: room_placement.vaults,
+        }
     }
 }
 
-// Params for the Conway's Game of Life Cell Automata which will be used to generate caves
-struct GameOfLifeParams {
-    survive_min: u8,
-    survive_max: u8,
-    resurrect_min: u8,
-    resurrect_max: u8,
-}
-
 // State for the Conway's Game of Life Cell Automata which will be used to generate caves
 struct GameOfLife {
     alive: Grid<bool>,
@@ -324,18 +523,14 @@ impl GameOfLife {
 }
 
 // Generate the starting point for the cave map by running a cell automata for several steps
-fn generate_initial_cave_map<R: Rng>(size: Size, rng: &mut R) -> Grid<FloorOrWall> {
-    const NUM_STEPS: usize = 10;
+fn generate_initial_cave_map<R: Rng>(
+    size: Size,
+    params: &CaveParams,
+    rng: &mut R,
+) -> Grid<FloorOrWall> {
     let mut game_of_life = GameOfLife::new(size, rng);
-    // This choice of params leads to cavernous regions of living cells
-    let params = GameOfLifeParams {
-        survive_min: 4,
-        survive_max: 8,
-        resurrect_min: 5,
-        resurrect_max: 5,
-    };
-    for _ in 0..NUM_STEPS {
-        game_of_life.step(&params);
+    for _ in 0..params.num_steps {
+        game_of_life.step(&params.game_of_life);
     }
     Grid::new_grid_map(game_of_life.alive, |alive| {
         if alive {
@@ -390,15 +585,15 @@ fn remove_disconnected_walls(map: &mut Grid<FloorOrWall>) {
 }
 
 // Returns a grid of cells defining a cave map
-fn generate_cave_map<R: Rng>(size: Size, rng: &mut R) -> Grid<FloorOrWall> {
-    let mut map = generate_initial_cave_map(size, rng);
+fn generate_cave_map<R: Rng>(size: Size, params: &CaveParams, rng: &mut R) -> Grid<FloorOrWall> {
+    let mut map = generate_initial_cave_map(size, params, rng);
     surround_map_with_walls(&mut map);
     remove_disconnected_walls(&mut map);
     map
 }
 
 // A cell of the game world
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum LevelCell {
     Floor,
     Wall,
@@ -409,17 +604,11 @@ enum LevelCell {
 
 impl LevelCell {
     fn is_wall(&self) -> bool {
-        match self {
-            Self::Wall | Self::CaveWall => true,
-            _ => false,
-        }
+        matches!(self, Self::Wall | Self::CaveWall)
     }
 
     fn is_floor(&self) -> bool {
-        match self {
-            Self::Floor | Self::CaveFloor => true,
-            _ => false,
-        }
+        matches!(self, Self::Floor | Self::CaveFloor)
     }
 }
 
@@ -430,6 +619,25 @@ fn is_surrounded_by_walls(map: &Grid<RoomsAndCorridorsCell>, coord: Coord) -> bo
         .all(|&cell| cell == RoomsAndCorridorsCell::Wall)
 }
 
+// Converts a cell of a map of rooms and corridors into a cell of the game world. Walls which are
+// entirely surrounded by other walls become cave walls.
+fn level_cell_from_rooms_and_corridors_cell(
+    rooms_and_corridors_level_map: &Grid<RoomsAndCorridorsCell>,
+    coord: Coord,
+) -> LevelCell {
+    match rooms_and_corridors_level_map.get_checked(coord) {
+        RoomsAndCorridorsCell::Floor => LevelCell::Floor,
+        RoomsAndCorridorsCell::Door => LevelCell::Door,
+        RoomsAndCorridorsCell::Wall => {
+            if is_surrounded_by_walls(rooms_and_corridors_level_map, coord) {
+                LevelCell::CaveWall
+            } else {
+                LevelCell::Wall
+            }
+        }
+    }
+}
+
 // Combines a map of rooms and corridors with a cave map to produce a hybrid of the two
 fn combine_rooms_and_corridors_level_with_cave(
     rooms_and_corridors_level_map: &Grid<RoomsAndCorridorsCell>,
@@ -437,17 +645,9 @@ fn combine_rooms_and_corridors_level_with_cave(
 ) -> Grid<LevelCell> {
     Grid::new_fn(cave_map.size(), |coord| match cave_map.get_checked(coord) {
         FloorOrWall::Floor => LevelCell::CaveFloor,
-        FloorOrWall::Wall => match rooms_and_corridors_level_map.get_checked(coord) {
-            RoomsAndCorridorsCell::Floor => LevelCell::Floor,
-            RoomsAndCorridorsCell::Door => LevelCell::Door,
-            RoomsAndCorridorsCell::Wall => {
-                if is_surrounded_by_walls(rooms_and_corridors_level_map, coord) {
-                    LevelCell::CaveWall
-                } else {
-                    LevelCell::Wall
-                }
-            }
-        },
+        FloorOrWall::Wall => {
+            level_cell_from_rooms_and_corridors_cell(rooms_and_corridors_level_map, coord)
+        }
     })
 }
 
@@ -527,27 +727,25 @@ fn remove_invalid_doors(map: &mut Grid<LevelCell>) {
 
 // Returns a grid of booleans, where a true value indicates that grass can spawn at that location.
 // The grid is populated using perlin noise.
-fn make_grass_map<R: Rng>(size: Size, rng: &mut R) -> Grid<bool> {
+fn make_grass_map<R: Rng>(size: Size, params: &GrassParams, rng: &mut R) -> Grid<bool> {
     let perlin = Perlin2::new(rng);
-    let zoom = 10.;
     Grid::new_fn(size, |Coord { x, y }| {
-        let x = x as f64 / zoom;
-        let y = y as f64 / zoom;
+        let x = x as f64 / params.perlin_zoom;
+        let y = y as f64 / params.perlin_zoom;
         let noise = perlin.noise((x, y));
-        noise > 0. && rng.gen::<f64>() > 0.5
+        noise > params.noise_threshold && rng.gen::<f64>() > params.sparseness
     })
 }
 
 // Returns a grid of booleans, where a true value indicates that grass can spawn at that location.
 // The grid is populated using perlin noise.
-fn make_water_map<R: Rng>(size: Size, rng: &mut R) -> Grid<bool> {
+fn make_water_map<R: Rng>(size: Size, params: &WaterParams, rng: &mut R) -> Grid<bool> {
     let perlin = Perlin2::new(rng);
-    let zoom = 7.;
     let mut map = Grid::new_fn(size, |Coord { x, y }| {
-        let x = x as f64 / zoom;
-        let y = y as f64 / zoom;
+        let x = x as f64 / params.perlin_zoom;
+        let y = y as f64 / params.perlin_zoom;
         let noise = perlin.noise01((x, y));
-        noise > 0.65
+        noise > params.noise_threshold
     });
     let mut to_visit = map
         .edge_enumerate()
@@ -570,6 +768,293 @@ fn make_water_map<R: Rng>(size: Size, rng: &mut R) -> Grid<bool> {
     map
 }
 
+// Updates a water map so that most walls which would be covered in water remain as walls. This
+// must happen before unreachable floor is removed, as the remaining walls can cut off parts of the
+// map.
+fn keep_most_walls_dry<R: Rng>(
+    map: &Grid<LevelCell>,
+    water_map: &mut Grid<bool>,
+    params: &WaterParams,
+    rng: &mut R,
+) {
+    for (&cell, water_cell) in map.iter().zip(water_map.iter_mut()) {
+        if *water_cell
+            && cell == LevelCell::Wall
+            && rng.gen_range(0..100) < params.wall_stays_dry_percent
+        {
+            *water_cell = false;
+        }
+    }
+}
+
+// Choose where monsters start. They are placed on dry floor, far enough from the player that the
+// player has a chance to sneak past them.
+fn choose_monster_spawns<R: Rng>(
+    map: &Grid<LevelCell>,
+    water_map: &Grid<bool>,
+    player_spawn: Coord,
+    params: &MonsterParams,
+    rng: &mut R,
+) -> Vec<Coord> {
+    let min_distance_squared = params.min_distance_from_player.pow(2);
+    let candidates = map
+        .enumerate()
+        .filter(|&(coord, cell)| {
+            cell.is_floor()
+                && !*water_map.get_checked(coord)
+                && (coord - player_spawn).magnitude2() >= min_distance_squared
+        })
+        .map(|(coord, _)| coord)
+        .collect::<Vec<_>>();
+    candidates
+        .choose_multiple(rng, params.count)
+        .cloned()
+        .collect()
+}
+
+// The layout of a level, before water and grass are added
+struct LevelLayout {
+    map: Grid<LevelCell>,
+    // Location where the player will start
+    player_spawn: Coord,
+    // Vaults placed in the level, whose water and grass take priority over the randomly-generated
+    // water and grass
+    vaults: Vec<PlacedVault>,
+}
+
+// An algorithm for generating the layout of a level
+trait LevelGenerator {
+    fn generate(&self, size: Size, params: &GenerationParams, rngs: &mut RngStreams)
+        -> LevelLayout;
+}
+
+// Generates levels made up of rooms and corridors, blended with a cave generated by a cell automata
+struct RoomsAndCaves;
+
+impl LevelGenerator for RoomsAndCaves {
+    fn generate(
+        &self,
+        size: Size,
+        params: &GenerationParams,
+        rngs: &mut RngStreams,
+    ) -> LevelLayout {
+        let RoomsAndCorridorsLevel {
+            map: rooms_and_corridors_map,
+            player_spawn,
+            vaults,
+        } = RoomsAndCorridorsLevel::generate(size, &params.rooms, rngs);
+        let cave_map = generate_cave_map(size, &params.cave, rngs.get(RngStream::Layout));
+        let mut map =
+            combine_rooms_and_corridors_level_with_cave(&rooms_and_corridors_map, &cave_map);
+        // Stamp vaults over the top of the cave so they keep their hand-authored shape
+        for vault in &vaults {
+            for (coord, cell) in vault.cells() {
+                *map.get_checked_mut(coord) = cell.level_cell;
+            }
+        }
+        LevelLayout {
+            map,
+            player_spawn,
+            vaults,
+        }
+    }
+}
+
+// The different algorithms which can be used to generate levels
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GeneratorKind {
+    RoomsAndCaves,
+    Bsp,
+    DrunkardsWalk,
+    CellularAutomataCave,
+}
+
+impl GeneratorKind {
+    pub const ALL: &'static [Self] = &[
+        Self::RoomsAndCaves,
+        Self::Bsp,
+        Self::DrunkardsWalk,
+        Self::CellularAutomataCave,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::RoomsAndCaves => "rooms-and-caves",
+            Self::Bsp => "bsp",
+            Self::DrunkardsWalk => "drunkards-walk",
+            Self::CellularAutomataCave => "cave",
+        }
+    }
+
+    fn generate_layout(
+        self,
+        size: Size,
+        params: &GenerationParams,
+        rngs: &mut RngStreams,
+    ) -> LevelLayout {
+        match self {
+            Self::RoomsAndCaves => RoomsAndCaves.generate(size, params, rngs),
+            Self::Bsp => bsp::Bsp.generate(size, params, rngs),
+            Self::DrunkardsWalk => drunkards_walk::DrunkardsWalk.generate(size, params, rngs),
+            Self::CellularAutomataCave => {
+                cellular_automata_cave::CellularAutomataCave.generate(size, params, rngs)
+            }
+        }
+    }
+}
+
+// Which generator to use for each level
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GeneratorChoice {
+    Kind(GeneratorKind),
+    // Choose a different generator at random for each level
+    Random,
+}
+
+impl GeneratorChoice {
+    fn choose_kind<R: Rng>(self, rng: &mut R) -> GeneratorKind {
+        match self {
+            Self::Kind(kind) => kind,
+            Self::Random => *GeneratorKind::ALL.choose(rng).unwrap(),
+        }
+    }
+}
+
+impl Default for GeneratorChoice {
+    fn default() -> Self {
+        Self::Kind(GeneratorKind::RoomsAndCaves)
+    }
+}
+
+impl fmt::Display for GeneratorChoice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Kind(kind) => write!(f, "{}", kind.name()),
+            Self::Random => write!(f, "random"),
+        }
+    }
+}
+
+impl FromStr for GeneratorChoice {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "random" {
+            return Ok(Self::Random);
+        }
+        GeneratorKind::ALL
+            .iter()
+            .find(|kind| kind.name() == s)
+            .map(|&kind| Self::Kind(kind))
+            .ok_or_else(|| format!("unknown generator: {}", s))
+    }
+}
+
+// Intermediate representation of a level produced by terrain generation, describing what will
+// be spawned at each location
+struct Level {
+    map: Grid<LevelCell>,
+    // Whether each cell is covered in water
+    water_map: Grid<bool>,
+    // Whether each cell may contain grass
+    grass_map: Grid<bool>,
+    player_spawn: Coord,
+    monster_spawns: Vec<Coord>,
+    // Entities placed by vaults, along with the names of their templates
+    vault_spawns: Vec<(Coord, String)>,
+}
+
+impl Level {
+    fn generate(
+        size: Size,
+        kind: GeneratorKind,
+        params: &GenerationParams,
+        rngs: &mut RngStreams,
+    ) -> Self {
+        let LevelLayout {
+            mut map,
+            player_spawn,
+            vaults,
+        } = kind.generate_layout(size, params, rngs);
+        let water_rng = rngs.get(RngStream::Water);
+        let mut water_map = make_water_map(size, &params.water, water_rng);
+        keep_most_walls_dry(&map, &mut water_map, &params.water, water_rng);
+        for (coord, cell) in vaults.iter().flat_map(PlacedVault::cells) {
+            *water_map.get_checked_mut(coord) = cell.water;
+        }
+        remove_unreachable_floor(&mut map, &mut water_map, player_spawn);
+        remove_invalid_doors(&mut map);
+        let mut grass_map = make_grass_map(size, &params.grass, rngs.get(RngStream::Grass));
+        for (coord, cell) in vaults.iter().flat_map(PlacedVault::cells) {
+            *grass_map.get_checked_mut(coord) = cell.grass;
+        }
+        // Parts of a vault may have been removed if they couldn't be reached
+        let vault_spawns = vaults
+            .iter()
+            .flat_map(PlacedVault::cells)
+            .filter_map(|(coord, cell)| {
+                let template_name = cell.spawn.as_ref()?;
+                let is_floor = !map.get_checked(coord).is_wall() || *water_map.get_checked(coord);
+                is_floor.then(|| (coord, template_name.clone()))
+            })
+            .collect::<Vec<_>>();
+        let mut monster_spawns = choose_monster_spawns(
+            &map,
+            &water_map,
+            player_spawn,
+            &params.monsters,
+            rngs.get(RngStream::Monsters),
+        );
+        monster_spawns.retain(|&coord| {
+            vault_spawns
+                .iter()
+                .all(|&(vault_coord, _)| vault_coord != coord)
+        });
+        let level = Self {
+            map,
+            water_map,
+            grass_map,
+            player_spawn,
+            monster_spawns,
+            vault_spawns,
+        };
+        level.log_stats(kind, vaults.len());
+        level
+    }
+
+    // Record a summary of the level's contents in the log
+    fn log_stats(&self, kind: GeneratorKind, num_vaults: usize) {
+        let mut floor = 0;
+        let mut doors = 0;
+        let mut water = 0;
+        let mut grass = 0;
+        for (coord, &cell) in self.map.enumerate() {
+            let is_water = *self.water_map.get_checked(coord);
+            floor += cell.is_floor() as usize;
+            doors += (cell == LevelCell::Door) as usize;
+            water += is_water as usize;
+            // Grass only grows on cave floor, or where water has replaced a cave wall
+            let can_have_grass =
+                cell == LevelCell::CaveFloor || (is_water && cell == LevelCell::CaveWall);
+            grass += (*self.grass_map.get_checked(coord) && can_have_grass) as usize;
+        }
+        info!(
+            "Generated {} level ({}x{}) with {} vaults: {} floor, {} doors, {} water, {} grass, \
+                {} monsters, {} vault spawns, player spawn at {:?}",
+            kind.name(),
+            self.map.width(),
+            self.map.height(),
+            num_vaults,
+            floor,
+            doors,
+            water,
+            grass,
+            self.monster_spawns.len(),
+            self.vault_spawns.len(),
+            self.player_spawn,
+        );
+    }
+}
+
 // Level representation produced by terrain generation
 pub struct Terrain {
     pub world: World,
@@ -577,57 +1062,67 @@ pub struct Terrain {
 }
 
 impl Terrain {
-    pub fn generate<R: Rng>(world_size: Size, rng: &mut R) -> Self {
+    pub fn generate(
+        world_size: Size,
+        generator: GeneratorChoice,
+        params: &GenerationParams,
+        rngs: &mut RngStreams,
+    ) -> Result<Self, SpawnError> {
         let mut world = World::new(world_size);
-        let RoomsAndCorridorsLevel {
-            map: rooms_and_corridors_map,
+        let kind = generator.choose_kind(rngs.get(RngStream::Layout));
+        let Level {
+            map,
+            water_map,
+            grass_map,
             player_spawn,
-        } = RoomsAndCorridorsLevel::generate(world_size, rng);
-        let cave_map = generate_cave_map(world_size, rng);
-        let mut combined_map =
-            combine_rooms_and_corridors_level_with_cave(&rooms_and_corridors_map, &cave_map);
-        let mut water_map = make_water_map(world_size, rng);
-        remove_unreachable_floor(&mut combined_map, &mut water_map, player_spawn);
-        remove_invalid_doors(&mut combined_map);
-        let grass_map = make_grass_map(world_size, rng);
-        let player_entity = world.spawn_player(player_spawn);
-        for (coord, &cell) in combined_map.enumerate() {
+            monster_spawns,
+            vault_spawns,
+        } = Level::generate(world_size, kind, params, rngs);
+        let rng = rngs.get(RngStream::Spawning);
+        let player_entity = world.spawn_from_template("player", player_spawn, rng)?;
+        for (coord, &cell) in map.enumerate() {
             use LevelCell::*;
-            if *water_map.get_checked(coord) {
-                match cell {
-                    Floor | Door => world.spawn_water(coord, rng),
-                    Wall => {
-                        if rng.gen_range(0..100) < 75 {
-                            world.spawn_wall(coord)
-                        } else {
-                            world.spawn_water(coord, rng);
-                        }
-                    }
-                    CaveFloor | CaveWall => {
-                        world.spawn_water(coord, rng);
-                        if *grass_map.get_checked(coord) {
-                            world.spawn_grass(coord);
-                        }
-                    }
-                }
-            } else {
-                match cell {
-                    Floor => world.spawn_floor(coord),
-                    Wall => world.spawn_wall(coord),
-                    Door => world.spawn_door(coord),
-                    CaveFloor => {
-                        world.spawn_cave_floor(coord);
-                        if *grass_map.get_checked(coord) {
-                            world.spawn_grass(coord);
-                        }
-                    }
-                    CaveWall => world.spawn_cave_wall(coord),
+            let water = *water_map.get_checked(coord);
+            let template_name = match cell {
+                _ if water => "water",
+                Floor => "floor",
+                Wall => "wall",
+                Door => "door",
+                CaveFloor => "cave_floor",
+                CaveWall => "cave_wall",
+            };
+            // A cell which fails to spawn is left out rather than abandoning the whole level
+            if let Err(e) = world.spawn_from_template(template_name, coord, rng) {
+                warn!("Skipping {} at {:?}: {}", template_name, coord, e);
+            }
+            // Grass grows in caves, including on cave walls which have been flooded
+            let grass = match cell {
+                CaveFloor => true,
+                CaveWall => water,
+                Floor | Wall | Door => false,
+            };
+            if grass && *grass_map.get_checked(coord) {
+                if let Err(e) = world.spawn_from_template("grass", coord, rng) {
+                    warn!("Skipping grass at {:?}: {}", coord, e);
                 }
             }
         }
-        Self {
+        for coord in monster_spawns {
+            if let Err(e) = world.spawn_from_template("goblin", coord, rng) {
+                warn!("Skipping goblin at {:?}: {}", coord, e);
+            }
+        }
+        for (coord, template_name) in vault_spawns {
+            if let Err(e) = world.spawn_from_template(&template_name, coord, rng) {
+                warn!("Skipping {} at {:?}: {}", template_name, coord, e);
+            }
+        }
+        Ok(Self {
             world,
             player_entity,
-        }
+        })
     }
 }
+
+#[cfg(test)]
+mod test;
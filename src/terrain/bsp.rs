@@ -0,0 +1,107 @@
+use super::{
+    level_cell_from_rooms_and_corridors_cell, params::BspParams, GenerationParams, LevelGenerator,
+    LevelLayout, Rect, Room, RoomPlacement, RoomsAndCorridorsLevel,
+};
+use crate::rng::{RngStream, RngStreams};
+use gridbugs::{
+    coord_2d::{Axis, Coord, Size},
+    grid_2d::Grid,
+};
+use rand::Rng;
+
+// Generates levels by recursively partitioning the map into rectangles, placing a room inside each
+// partition, and connecting each room to the room in the previous partition with a corridor
+pub struct Bsp;
+
+// Recursively split a rectangle in two along its longest axis until the pieces are small enough,
+// adding each of the final pieces to `partitions`. Partitions are added in the order they appear
+// in the tree, so consecutive partitions are close to one another.
+fn partition<R: Rng>(rect: Rect, params: &BspParams, rng: &mut R, partitions: &mut Vec<Rect>) {
+    let axis = if rect.size.width() >= rect.size.height() {
+        Axis::X
+    } else {
+        Axis::Y
+    };
+    let length = rect.size.get(axis);
+    let can_split = length >= params.min_partition_size * 2;
+    let must_split = length > params.max_partition_size;
+    if !can_split || (!must_split && rng.gen::<bool>()) {
+        partitions.push(rect);
+        return;
+    }
+    let split = rng.gen_range(params.min_partition_size..=(length - params.min_partition_size));
+    let first = Rect {
+        top_left: rect.top_left,
+        size: rect.size.set(axis, split),
+    };
+    let second = Rect {
+        top_left: rect.top_left + Coord::new_axis(split as i32, 0, axis),
+        size: rect.size.set(axis, length - split),
+    };
+    partition(first, params, rng, partitions);
+    partition(second, params, rng, partitions);
+}
+
+// Randomly choose a room which fits inside a partition without touching its far edges, so rooms in
+// neighbouring partitions never share walls
+fn choose_room_in_partition<R: Rng>(partition: Rect, params: &BspParams, rng: &mut R) -> Room {
+    let max_size = Size::new(
+        params.max_room_size.width.min(partition.size.width()),
+        params.max_room_size.height.min(partition.size.height()),
+    );
+    let rect = Rect::choose(
+        partition.size,
+        params.min_room_size.to_size(),
+        max_size,
+        rng,
+    );
+    Room {
+        rect: Rect {
+            top_left: partition.top_left + rect.top_left,
+            size: rect.size,
+        },
+    }
+}
+
+impl LevelGenerator for Bsp {
+    fn generate(
+        &self,
+        size: Size,
+        params: &GenerationParams,
+        rngs: &mut RngStreams,
+    ) -> LevelLayout {
+        let rng = rngs.get(RngStream::Layout);
+        let params = &params.bsp;
+        let mut partitions = Vec::new();
+        partition(
+            Rect {
+                top_left: Coord::new(0, 0),
+                size,
+            },
+            params,
+            rng,
+            &mut partitions,
+        );
+        let mut room_placement = RoomPlacement::new(size);
+        let mut previous_room = None;
+        for partition in partitions {
+            let room = choose_room_in_partition(partition, params, rng);
+            let existing_rooms = previous_room.iter().cloned().collect::<Vec<_>>();
+            room_placement.add_room_connected_to(room, &existing_rooms, rng);
+            previous_room = Some(room);
+        }
+        let RoomsAndCorridorsLevel {
+            map: rooms_and_corridors_map,
+            player_spawn,
+            vaults,
+        } = RoomsAndCorridorsLevel::from_room_placement(room_placement, rngs);
+        let map = Grid::new_fn(size, |coord| {
+            level_cell_from_rooms_and_corridors_cell(&rooms_and_corridors_map, coord)
+        });
+        LevelLayout {
+            map,
+            player_spawn,
+            vaults,
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use super::{
+    generate_cave_map, FloorOrWall, GenerationParams, LevelCell, LevelGenerator, LevelLayout,
+};
+use crate::rng::{RngStream, RngStreams};
+use gridbugs::{
+    coord_2d::{Axis, Coord, Size},
+    direction::CardinalDirection,
+    grid_2d::Grid,
+};
+use rand::{seq::SliceRandom, Rng};
+
+// Generates levels made entirely of cave, using only the cell automata from `RoomsAndCaves`. The
+// cell automata tends to produce many disconnected caves, so tunnels are dug to connect them.
+pub struct CellularAutomataCave;
+
+// Returns a list of regions of floor cells connected by cardinal directions, largest first
+fn floor_regions(map: &Grid<FloorOrWall>) -> Vec<Vec<Coord>> {
+    let mut seen = Grid::new_copy(map.size(), false);
+    let mut regions = Vec::new();
+    for (start, &cell) in map.enumerate() {
+        if cell != FloorOrWall::Floor || *seen.get_checked(start) {
+            continue;
+        }
+        *seen.get_checked_mut(start) = true;
+        let mut region = Vec::new();
+        let mut to_visit = vec![start];
+        while let Some(coord) = to_visit.pop() {
+            region.push(coord);
+            for direction in CardinalDirection::all() {
+                let neighbour_coord = coord + direction.coord();
+                if let Some(FloorOrWall::Floor) = map.get(neighbour_coord) {
+                    let seen_cell = seen.get_checked_mut(neighbour_coord);
+                    if !*seen_cell {
+                        *seen_cell = true;
+                        to_visit.push(neighbour_coord);
+                    }
+                }
+            }
+        }
+        regions.push(region);
+    }
+    regions.sort_by_key(|region| std::cmp::Reverse(region.len()));
+    regions
+}
+
+// Carve an L-shaped tunnel from start to end, first traversing the given axis
+fn dig_tunnel(map: &mut Grid<FloorOrWall>, start: Coord, end: Coord, first_axis: Axis) {
+    let corner = Coord::new_axis(
+        end.get(first_axis),
+        start.get(first_axis.other()),
+        first_axis,
+    );
+    for (from, to) in [(start, corner), (corner, end)] {
+        let delta = to - from;
+        let step = Coord::new(delta.x.signum(), delta.y.signum());
+        let mut current = from;
+        *map.get_checked_mut(current) = FloorOrWall::Floor;
+        while current != to {
+            current += step;
+            *map.get_checked_mut(current) = FloorOrWall::Floor;
+        }
+    }
+}
+
+impl LevelGenerator for CellularAutomataCave {
+    fn generate(
+        &self,
+        size: Size,
+        params: &GenerationParams,
+        rngs: &mut RngStreams,
+    ) -> LevelLayout {
+        let rng = rngs.get(RngStream::Layout);
+        let mut cave_map = generate_cave_map(size, &params.cave, rng);
+        let regions = floor_regions(&cave_map);
+        let player_spawn = if let Some((largest_region, other_regions)) = regions.split_first() {
+            for region in other_regions {
+                if region.len() < params.cellular_automata_cave.min_connected_region_size {
+                    break;
+                }
+                let start = *region.choose(rng).unwrap();
+                let end = *largest_region.choose(rng).unwrap();
+                let axis = if rng.gen() { Axis::X } else { Axis::Y };
+                dig_tunnel(&mut cave_map, start, end, axis);
+            }
+            // The player starts somewhere in the largest cave
+            *largest_region.choose(rng).unwrap()
+        } else {
+            // In the unlikely event that the cave has no floor at all, carve out the centre
+            let centre = size.to_coord().unwrap() / 2;
+            *cave_map.get_checked_mut(centre) = FloorOrWall::Floor;
+            centre
+        };
+        let map = Grid::new_grid_map(cave_map, |floor_or_wall| match floor_or_wall {
+            FloorOrWall::Floor => LevelCell::CaveFloor,
+            FloorOrWall::Wall => LevelCell::CaveWall,
+        });
+        LevelLayout {
+            map,
+            player_spawn,
+            vaults: Vec::new(),
+        }
+    }
+}
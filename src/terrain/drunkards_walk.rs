@@ -0,0 +1,48 @@
+use super::{GenerationParams, LevelCell, LevelGenerator, LevelLayout};
+use crate::rng::{RngStream, RngStreams};
+use gridbugs::{coord_2d::Size, direction::CardinalDirection, grid_2d::Grid};
+use rand::seq::SliceRandom;
+
+// Generates winding caverns by carving out the path of a random walk starting from the centre of
+// the map
+pub struct DrunkardsWalk;
+
+impl LevelGenerator for DrunkardsWalk {
+    fn generate(
+        &self,
+        size: Size,
+        params: &GenerationParams,
+        rngs: &mut RngStreams,
+    ) -> LevelLayout {
+        let rng = rngs.get(RngStream::Layout);
+        let mut map = Grid::new_copy(size, LevelCell::CaveWall);
+        let player_spawn = size.to_coord().unwrap() / 2;
+        // The walk can't carve out the edge of the map, so the target is capped at the number of
+        // non-edge cells
+        let num_internal_cells = (size.width() - 2) * (size.height() - 2);
+        let target_num_floor_cells = ((size.count() as u32 * params.drunkards_walk.floor_percent)
+            / 100)
+            .min(num_internal_cells);
+        let mut num_floor_cells = 0;
+        let mut current = player_spawn;
+        let directions = CardinalDirection::all().collect::<Vec<_>>();
+        while num_floor_cells < target_num_floor_cells {
+            let cell = map.get_checked_mut(current);
+            if *cell == LevelCell::CaveWall {
+                *cell = LevelCell::CaveFloor;
+                num_floor_cells += 1;
+            }
+            let direction = directions.choose(rng).unwrap();
+            let next = current + direction.coord();
+            // Never carve out the edge of the map, so the level is surrounded by walls
+            if !size.is_on_edge(next) && next.is_valid(size) {
+                current = next;
+            }
+        }
+        LevelLayout {
+            map,
+            player_spawn,
+            vaults: Vec::new(),
+        }
+    }
+}
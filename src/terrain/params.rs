@@ -0,0 +1,313 @@
+use gridbugs::coord_2d::Size;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+// The width and height of a rectangle, in cells
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Dimensions {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Dimensions {
+    pub fn to_size(self) -> Size {
+        Size::new(self.width, self.height)
+    }
+}
+
+// Params for the room-placement algorithm used by the rooms-and-caves generator
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RoomParams {
+    pub num_room_attempts: usize,
+    // Room sizes include their walls. The maximum is exclusive.
+    pub min_room_size: Dimensions,
+    pub max_room_size: Dimensions,
+    // Chance that each room attempt places a hand-authored vault instead of a plain room
+    pub vault_chance_percent: u32,
+}
+
+impl Default for RoomParams {
+    fn default() -> Self {
+        Self {
+            num_room_attempts: 50,
+            min_room_size: Dimensions {
+                width: 5,
+                height: 5,
+            },
+            max_room_size: Dimensions {
+                width: 11,
+                height: 9,
+            },
+            vault_chance_percent: 10,
+        }
+    }
+}
+
+// Params for the Conway's Game of Life Cell Automata which will be used to generate caves
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameOfLifeParams {
+    pub survive_min: u8,
+    pub survive_max: u8,
+    pub resurrect_min: u8,
+    pub resurrect_max: u8,
+}
+
+impl Default for GameOfLifeParams {
+    // This choice of params leads to cavernous regions of living cells
+    fn default() -> Self {
+        Self {
+            survive_min: 4,
+            survive_max: 8,
+            resurrect_min: 5,
+            resurrect_max: 5,
+        }
+    }
+}
+
+// Params for generating caves with a cell automata
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CaveParams {
+    pub num_steps: usize,
+    pub game_of_life: GameOfLifeParams,
+}
+
+impl Default for CaveParams {
+    fn default() -> Self {
+        Self {
+            num_steps: 10,
+            game_of_life: GameOfLifeParams::default(),
+        }
+    }
+}
+
+// Params for the bsp generator
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BspParams {
+    // Partitions are never split into pieces smaller than this
+    pub min_partition_size: u32,
+    // Partitions larger than this are always split
+    pub max_partition_size: u32,
+    pub min_room_size: Dimensions,
+    pub max_room_size: Dimensions,
+}
+
+impl Default for BspParams {
+    fn default() -> Self {
+        Self {
+            min_partition_size: 8,
+            max_partition_size: 20,
+            min_room_size: Dimensions {
+                width: 5,
+                height: 5,
+            },
+            max_room_size: Dimensions {
+                width: 11,
+                height: 9,
+            },
+        }
+    }
+}
+
+// Params for the drunkard's walk generator
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DrunkardsWalkParams {
+    // The walk stops once this proportion of the map has been carved out
+    pub floor_percent: u32,
+}
+
+impl Default for DrunkardsWalkParams {
+    fn default() -> Self {
+        Self { floor_percent: 40 }
+    }
+}
+
+// Params for the cell-automata-only cave generator
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CellularAutomataCaveParams {
+    // Regions of cave smaller than this are left disconnected (and will be filled in later)
+    pub min_connected_region_size: usize,
+}
+
+impl Default for CellularAutomataCaveParams {
+    fn default() -> Self {
+        Self {
+            min_connected_region_size: 10,
+        }
+    }
+}
+
+// Params for placing water using perlin noise
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WaterParams {
+    // Larger values lead to larger bodies of water
+    pub perlin_zoom: f64,
+    // Water is placed where the noise (between 0 and 1) exceeds this value
+    pub noise_threshold: f64,
+    // The chance that a wall which would be covered by water remains as a wall
+    pub wall_stays_dry_percent: u32,
+}
+
+impl Default for WaterParams {
+    fn default() -> Self {
+        Self {
+            perlin_zoom: 7.,
+            noise_threshold: 0.65,
+            wall_stays_dry_percent: 75,
+        }
+    }
+}
+
+// Params for placing grass using perlin noise
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GrassParams {
+    // Larger values lead to larger patches of grass
+    pub perlin_zoom: f64,
+    // Grass may be placed where the noise (between -1 and 1) exceeds this value
+    pub noise_threshold: f64,
+    // Within patches, grass is placed where a random number (between 0 and 1) exceeds this value
+    pub sparseness: f64,
+}
+
+impl Default for GrassParams {
+    fn default() -> Self {
+        Self {
+            perlin_zoom: 10.,
+            noise_threshold: 0.,
+            sparseness: 0.5,
+        }
+    }
+}
+
+// Params for placing monsters in generated levels
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MonsterParams {
+    pub count: usize,
+    // Monsters are never placed closer than this to the player's starting position
+    pub min_distance_from_player: u32,
+}
+
+impl Default for MonsterParams {
+    fn default() -> Self {
+        Self {
+            count: 3,
+            min_distance_from_player: 10,
+        }
+    }
+}
+
+// All the params which control level generation
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GenerationParams {
+    pub rooms: RoomParams,
+    pub cave: CaveParams,
+    pub bsp: BspParams,
+    pub drunkards_walk: DrunkardsWalkParams,
+    pub cellular_automata_cave: CellularAutomataCaveParams,
+    pub water: WaterParams,
+    pub grass: GrassParams,
+    pub monsters: MonsterParams,
+}
+
+// Checks that a range of room sizes can be used to randomly choose rooms
+fn validate_room_sizes(name: &str, min: Dimensions, max: Dimensions) -> Result<(), String> {
+    if min.width < 3 || min.height < 3 {
+        return Err(format!("{}: min_room_size must be at least 3x3", name));
+    }
+    if min.width >= max.width || min.height >= max.height {
+        return Err(format!(
+            "{}: max_room_size must be larger than min_room_size",
+            name
+        ));
+    }
+    Ok(())
+}
+
+impl GenerationParams {
+    // Reads params from a toml file. Any params missing from the file take their default values.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        toml::from_str(&contents).map_err(|e| format!("failed to parse {}: {}", path.display(), e))
+    }
+
+    pub fn to_toml_string(&self) -> String {
+        // Converted to a `toml::Value` first since it writes plain values before tables, which
+        // toml requires and which the fields of the params don't always do
+        let value = toml::Value::try_from(self).expect("failed to serialize generation params");
+        toml::to_string(&value).expect("failed to serialize generation params")
+    }
+
+    // Overrides a single param given a string of the form "path.to.param=value", where the value
+    // is written in toml syntax
+    pub fn apply_override(&mut self, param_override: &str) -> Result<(), String> {
+        let (key, value) = param_override
+            .split_once('=')
+            .ok_or_else(|| format!("expected KEY=VALUE, got {}", param_override))?;
+        let value = toml::from_str::<toml::Value>(&format!("value = {}", value.trim()))
+            .map_err(|e| format!("invalid value for {}: {}", key, e))?
+            .get("value")
+            .cloned()
+            .unwrap();
+        let mut params = toml::Value::try_from(&*self).unwrap();
+        let mut current = &mut params;
+        for component in key.trim().split('.') {
+            current = current
+                .get_mut(component)
+                .ok_or_else(|| format!("unknown param: {}", key))?;
+        }
+        *current = value;
+        *self = params
+            .try_into()
+            .map_err(|e| format!("invalid value for {}: {}", key, e))?;
+        Ok(())
+    }
+
+    // Returns an error if the params would cause level generation to fail for a world of the
+    // given size
+    pub fn validate(&self, world_size: Size) -> Result<(), String> {
+        validate_room_sizes("rooms", self.rooms.min_room_size, self.rooms.max_room_size)?;
+        // Rooms are placed entirely inside the world, and the maximum size is exclusive
+        if self.rooms.max_room_size.width > world_size.width()
+            || self.rooms.max_room_size.height > world_size.height()
+        {
+            return Err(format!(
+                "rooms: max_room_size must be at most the world size ({}x{})",
+                world_size.width(),
+                world_size.height()
+            ));
+        }
+        if self.rooms.num_room_attempts == 0 {
+            return Err("rooms: num_room_attempts must be at least 1".to_string());
+        }
+        validate_room_sizes("bsp", self.bsp.min_room_size, self.bsp.max_room_size)?;
+        if self.bsp.min_partition_size <= self.bsp.min_room_size.width
+            || self.bsp.min_partition_size <= self.bsp.min_room_size.height
+        {
+            return Err("bsp: min_partition_size must be larger than min_room_size".to_string());
+        }
+        if self.rooms.vault_chance_percent > 100 {
+            return Err("rooms: vault_chance_percent must be at most 100".to_string());
+        }
+        if self.drunkards_walk.floor_percent == 0 || self.drunkards_walk.floor_percent > 100 {
+            return Err("drunkards_walk: floor_percent must be between 1 and 100".to_string());
+        }
+        if self.water.perlin_zoom <= 0. || self.grass.perlin_zoom <= 0. {
+            return Err("perlin_zoom must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,135 @@
+use super::GenerationParams;
+use gridbugs::coord_2d::Size;
+use std::{fs, path::PathBuf};
+
+const WORLD_SIZE: Size = Size::new_u16(40, 30);
+
+// Writes a params file to a unique path in the temporary directory
+fn write_params_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "roguelike-params-test-{}-{}.toml",
+        std::process::id(),
+        name
+    ));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn override_sets_nested_param() {
+    let mut params = GenerationParams::default();
+    params
+        .apply_override("rooms.max_room_size.width=15")
+        .unwrap();
+    params.apply_override(" water.perlin_zoom = 0.5 ").unwrap();
+    assert_eq!(params.rooms.max_room_size.width, 15);
+    assert_eq!(params.water.perlin_zoom, 0.5);
+    assert!(params.validate(WORLD_SIZE).is_ok());
+}
+
+#[test]
+fn override_rejects_unknown_key() {
+    let mut params = GenerationParams::default();
+    let e = params.apply_override("rooms.no_such_param=1").unwrap_err();
+    assert!(e.contains("unknown param"), "{}", e);
+    assert!(params.apply_override("no_such_section.x=1").is_err());
+}
+
+#[test]
+fn override_rejects_missing_value() {
+    let mut params = GenerationParams::default();
+    assert!(params.apply_override("rooms.num_room_attempts").is_err());
+}
+
+#[test]
+fn override_rejects_wrong_type() {
+    let mut params = GenerationParams::default();
+    let e = params
+        .apply_override("rooms.num_room_attempts=\"lots\"")
+        .unwrap_err();
+    assert!(e.contains("invalid value"), "{}", e);
+    // A failed override leaves the params unchanged
+    assert_eq!(params.rooms.num_room_attempts, 50);
+}
+
+#[test]
+fn validate_rejects_invalid_ranges() {
+    let mut params = GenerationParams::default();
+    params.rooms.min_room_size.width = params.rooms.max_room_size.width;
+    assert!(params.validate(WORLD_SIZE).is_err());
+
+    let mut params = GenerationParams::default();
+    params.rooms.min_room_size.height = 2;
+    assert!(params.validate(WORLD_SIZE).is_err());
+
+    let mut params = GenerationParams::default();
+    params.rooms.vault_chance_percent = 101;
+    assert!(params.validate(WORLD_SIZE).is_err());
+
+    let mut params = GenerationParams::default();
+    params.drunkards_walk.floor_percent = 101;
+    assert!(params.validate(WORLD_SIZE).is_err());
+
+    let mut params = GenerationParams::default();
+    params.grass.perlin_zoom = 0.;
+    assert!(params.validate(WORLD_SIZE).is_err());
+}
+
+#[test]
+fn validate_rejects_params_which_would_generate_broken_levels() {
+    let mut params = GenerationParams::default();
+    params.rooms.num_room_attempts = 0;
+    let e = params.validate(WORLD_SIZE).unwrap_err();
+    assert!(e.contains("num_room_attempts"), "{}", e);
+
+    let mut params = GenerationParams::default();
+    params.drunkards_walk.floor_percent = 0;
+    let e = params.validate(WORLD_SIZE).unwrap_err();
+    assert!(e.contains("floor_percent"), "{}", e);
+}
+
+#[test]
+fn validate_rejects_rooms_larger_than_the_world() {
+    let mut params = GenerationParams::default();
+    params.rooms.max_room_size.width = WORLD_SIZE.width() + 1;
+    let e = params.validate(WORLD_SIZE).unwrap_err();
+    assert!(e.contains("max_room_size"), "{}", e);
+
+    let mut params = GenerationParams::default();
+    params.rooms.max_room_size.height = WORLD_SIZE.height() + 1;
+    assert!(params.validate(WORLD_SIZE).is_err());
+
+    // The maximum room size is exclusive, so rooms this big still fit
+    let mut params = GenerationParams::default();
+    params.rooms.max_room_size.width = WORLD_SIZE.width();
+    params.rooms.max_room_size.height = WORLD_SIZE.height();
+    assert!(params.validate(WORLD_SIZE).is_ok());
+}
+
+#[test]
+fn load_fills_in_missing_params_with_defaults() {
+    let path = write_params_file("partial", "[rooms]\nvault_chance_percent = 25\n");
+    let params = GenerationParams::load(&path);
+    fs::remove_file(&path).unwrap();
+    let params = params.unwrap();
+    assert_eq!(params.rooms.vault_chance_percent, 25);
+    assert_eq!(params.rooms.num_room_attempts, 50);
+}
+
+#[test]
+fn load_rejects_unknown_key() {
+    let path = write_params_file("unknown", "[rooms]\nno_such_param = 1\n");
+    let params = GenerationParams::load(&path);
+    fs::remove_file(&path).unwrap();
+    assert!(params.is_err());
+}
+
+#[test]
+fn load_round_trips_through_toml() {
+    let mut params = GenerationParams::default();
+    params.cave.num_steps = 7;
+    let path = write_params_file("round-trip", &params.to_toml_string());
+    let loaded = GenerationParams::load(&path);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.unwrap().cave.num_steps, 7);
+}
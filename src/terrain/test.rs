@@ -0,0 +1,204 @@
+use super::{
+    is_valid_door_position,
+    vault::{bundled_vaults, Vault},
+    GenerationParams, GeneratorKind, Level, LevelCell,
+};
+use crate::rng::RngStreams;
+use gridbugs::{
+    coord_2d::{Coord, Size},
+    direction::CardinalDirection,
+    grid_2d::Grid,
+};
+use proptest::prelude::*;
+
+fn generate_level(
+    seed: u64,
+    width: u32,
+    height: u32,
+    kind: GeneratorKind,
+    params: &GenerationParams,
+) -> Level {
+    let mut rngs = RngStreams::new(seed);
+    Level::generate(Size::new(width, height), kind, params, &mut rngs)
+}
+
+fn check_level_invariants(level: &Level) -> Result<(), TestCaseError> {
+    check_border_is_wall(level)?;
+    check_no_water_on_border(level)?;
+    check_player_spawns_on_floor(level)?;
+    check_all_floor_reachable_from_player_spawn(level)?;
+    check_doors_are_in_valid_positions(level)?;
+    Ok(())
+}
+
+// Returns true iff the player can stand at the given coord (possibly after opening a door)
+fn is_traversable(level: &Level, coord: Coord) -> bool {
+    !level.map.get_checked(coord).is_wall() || *level.water_map.get_checked(coord)
+}
+
+// Returns a grid where each cell is true iff it can be reached from the player spawn
+fn reachable_from_player_spawn(level: &Level) -> Grid<bool> {
+    let mut seen = Grid::new_copy(level.map.size(), false);
+    *seen.get_checked_mut(level.player_spawn) = true;
+    let mut to_visit = vec![level.player_spawn];
+    while let Some(coord) = to_visit.pop() {
+        for direction in CardinalDirection::all() {
+            let neighbour_coord = coord + direction.coord();
+            if let Some(seen_cell) = seen.get_mut(neighbour_coord) {
+                if !*seen_cell && is_traversable(level, neighbour_coord) {
+                    *seen_cell = true;
+                    to_visit.push(neighbour_coord);
+                }
+            }
+        }
+    }
+    seen
+}
+
+fn check_border_is_wall(level: &Level) -> Result<(), TestCaseError> {
+    for (coord, cell) in level.map.edge_enumerate() {
+        prop_assert!(cell.is_wall(), "non-wall cell on border at {:?}", coord);
+    }
+    Ok(())
+}
+
+fn check_no_water_on_border(level: &Level) -> Result<(), TestCaseError> {
+    for (coord, &water) in level.water_map.edge_enumerate() {
+        prop_assert!(!water, "water on border at {:?}", coord);
+    }
+    Ok(())
+}
+
+fn check_player_spawns_on_floor(level: &Level) -> Result<(), TestCaseError> {
+    prop_assert!(
+        level.map.get_checked(level.player_spawn).is_floor(),
+        "player spawns on non-floor cell at {:?}",
+        level.player_spawn
+    );
+    Ok(())
+}
+
+fn check_all_floor_reachable_from_player_spawn(level: &Level) -> Result<(), TestCaseError> {
+    let reachable = reachable_from_player_spawn(level);
+    for (coord, &reachable) in reachable.enumerate() {
+        if is_traversable(level, coord) {
+            prop_assert!(reachable, "unreachable cell at {:?}", coord);
+        }
+    }
+    Ok(())
+}
+
+fn check_doors_are_in_valid_positions(level: &Level) -> Result<(), TestCaseError> {
+    for (coord, &cell) in level.map.enumerate() {
+        if cell == LevelCell::Door {
+            prop_assert!(
+                is_valid_door_position(&level.map, coord),
+                "door in invalid position at {:?}",
+                coord
+            );
+        }
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2000))]
+
+    #[test]
+    fn generated_levels_satisfy_invariants(
+        seed: u64,
+        width in 20u32..100,
+        height in 15u32..80,
+        kind in proptest::sample::select(GeneratorKind::ALL),
+    ) {
+        let level = generate_level(seed, width, height, kind, &GenerationParams::default());
+        check_level_invariants(&level)?;
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(200))]
+
+    #[test]
+    fn levels_with_many_vaults_satisfy_invariants(
+        seed: u64,
+        width in 20u32..100,
+        height in 15u32..80,
+        vault_chance_percent in 50u32..=100,
+    ) {
+        let mut params = GenerationParams::default();
+        params.rooms.vault_chance_percent = vault_chance_percent;
+        let level = generate_level(seed, width, height, GeneratorKind::RoomsAndCaves, &params);
+        check_level_invariants(&level)?;
+    }
+}
+
+#[test]
+fn bundled_vaults_are_valid() {
+    // Panics if any of the bundled vaults fail to parse
+    assert!(!bundled_vaults().is_empty());
+}
+
+#[test]
+fn vault_legend_entries_are_parsed() {
+    let vault = Vault::parse(
+        "[legend]\n# = wall\n+ = door\n\" = cave_floor grass\ng = floor water spawn:goblin\n\n\
+         [map]\n#+#\n#\"#\n#g#\n###\n",
+    )
+    .unwrap();
+    let grass = vault.cells.get_checked(Coord::new(1, 1));
+    assert_eq!(grass.level_cell, LevelCell::CaveFloor);
+    assert!(grass.grass && !grass.water && grass.spawn.is_none());
+    let goblin = vault.cells.get_checked(Coord::new(1, 2));
+    assert_eq!(goblin.level_cell, LevelCell::Floor);
+    assert!(goblin.water);
+    assert_eq!(goblin.spawn.as_deref(), Some("goblin"));
+}
+
+#[test]
+fn vaults_with_bad_legends_are_rejected() {
+    let map = "[map]\n#+#\n#.#\n###\n";
+    for legend in [
+        "# = wall\n+ = door\n. = lava",
+        "# = wall\n+ = door\n. floor",
+        "# = wall\n+ = door\n. = floor cave_floor",
+        "# = wall\n+ = door\n. = water",
+        "# = wall\n+ = door\n. = floor grass",
+        "# = wall spawn:goblin\n+ = door\n. = floor",
+        "# = wall\n+ = door\n. = floor spawn:",
+        "# = wall\n+ = door\n. = floor spawn:goblin spawn:goblin",
+        // The map uses a character which isn't in the legend
+        "# = wall\n+ = door",
+    ] {
+        let text = format!("[legend]\n{}\n\n{}", legend, map);
+        assert!(Vault::parse(&text).is_err(), "accepted legend:\n{}", legend);
+    }
+}
+
+#[test]
+fn vaults_with_ragged_rows_are_rejected() {
+    let e = Vault::parse("[legend]\n# = wall\n+ = door\n. = floor\n\n[map]\n#+#\n#..#\n###\n")
+        .err()
+        .unwrap();
+    assert!(e.contains("same length"), "{}", e);
+}
+
+#[test]
+fn vaults_with_no_usable_cells_are_rejected() {
+    let legend = "[legend]\n# = wall\n+ = door\n. = floor\n\n[map]\n";
+    for map in [
+        // Too small to have an inside
+        "#+\n##\n",
+        // No way in
+        "###\n#.#\n###\n",
+        // The only door leads into a wall
+        "#+#\n###\n#.#\n###\n",
+        // Floor on the edge
+        "#+#\n#..\n###\n",
+        // Door in a corner
+        "+##\n#.#\n###\n",
+    ] {
+        let text = format!("{}{}", legend, map);
+        assert!(Vault::parse(&text).is_err(), "accepted map:\n{}", map);
+    }
+}
@@ -0,0 +1,287 @@
+use super::LevelCell;
+use gridbugs::{
+    coord_2d::{Coord, Size},
+    direction::CardinalDirection,
+    grid_2d::Grid,
+};
+use rand::Rng;
+use std::collections::HashMap;
+
+// Hand-authored vaults bundled with the game
+const BUNDLED_VAULTS: &[(&str, &str)] = &[
+    ("shrine", include_str!("vaults/shrine.txt")),
+    ("pillared_hall", include_str!("vaults/pillared_hall.txt")),
+    ("inner_sanctum", include_str!("vaults/inner_sanctum.txt")),
+    ("grotto", include_str!("vaults/grotto.txt")),
+];
+
+// A single cell of a vault
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VaultCell {
+    pub level_cell: LevelCell,
+    pub water: bool,
+    pub grass: bool,
+    // The name of an entity template (e.g. a monster) to spawn in the cell
+    pub spawn: Option<String>,
+}
+
+impl VaultCell {
+    // Parse a legend entry such as "cave_floor grass" or "floor spawn:goblin". Exactly one word
+    // must describe the cell itself, and the remaining words describe features and entities
+    // added on top of the cell.
+    fn parse(description: &str) -> Result<Self, String> {
+        let mut level_cell = None;
+        let mut water = false;
+        let mut grass = false;
+        let mut spawn = None;
+        for word in description.split_whitespace() {
+            if let Some(template_name) = word.strip_prefix("spawn:") {
+                if template_name.is_empty() {
+                    return Err(format!("missing template name: {}", description));
+                }
+                if spawn.replace(template_name.to_string()).is_some() {
+                    return Err(format!("multiple spawns in legend entry: {}", description));
+                }
+                continue;
+            }
+            let cell = match word {
+                "wall" => LevelCell::Wall,
+                "cave_wall" => LevelCell::CaveWall,
+                "floor" => LevelCell::Floor,
+                "cave_floor" => LevelCell::CaveFloor,
+                "door" => LevelCell::Door,
+                "water" => {
+                    water = true;
+                    continue;
+                }
+                "grass" => {
+                    grass = true;
+                    continue;
+                }
+                other => return Err(format!("unknown legend word: {}", other)),
+            };
+            if level_cell.replace(cell).is_some() {
+                return Err(format!("multiple cells in legend entry: {}", description));
+            }
+        }
+        let level_cell =
+            level_cell.ok_or_else(|| format!("no cell in legend entry: {}", description))?;
+        // Grass is only ever spawned on cave floor
+        if grass && level_cell != LevelCell::CaveFloor {
+            return Err(format!("grass must be on cave_floor: {}", description));
+        }
+        if spawn.is_some() && (level_cell.is_wall() || level_cell == LevelCell::Door) {
+            return Err(format!("entities can only spawn on floor: {}", description));
+        }
+        Ok(Self {
+            level_cell,
+            water,
+            grass,
+            spawn,
+        })
+    }
+}
+
+// A hand-authored room which can be stamped into a level. The outside of the vault must be made
+// of walls and doors, and corridors will only ever connect to the vault through its doors.
+#[derive(Clone)]
+pub struct Vault {
+    pub cells: Grid<VaultCell>,
+}
+
+impl Vault {
+    // Parse a vault from text of the form:
+    //
+    // [legend]
+    // # = wall
+    // . = floor
+    // + = door
+    // ~ = floor water
+    // g = floor spawn:goblin
+    //
+    // [map]
+    // ##+##
+    // #.~.#
+    // ##+##
+    pub fn parse(text: &str) -> Result<Self, String> {
+        enum Section {
+            None,
+            Legend,
+            Map,
+        }
+        let mut section = Section::None;
+        let mut legend = HashMap::new();
+        let mut rows = Vec::new();
+        for line in text.lines() {
+            match line.trim() {
+                "[legend]" => {
+                    section = Section::Legend;
+                    continue;
+                }
+                "[map]" => {
+                    section = Section::Map;
+                    continue;
+                }
+                _ => (),
+            }
+            match section {
+                Section::None => {
+                    if !line.trim().is_empty() {
+                        return Err(format!("unexpected line outside section: {}", line));
+                    }
+                }
+                Section::Legend => {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let mut chars = line.chars();
+                    let key = chars.next().unwrap();
+                    let description = chars
+                        .as_str()
+                        .trim_start()
+                        .strip_prefix('=')
+                        .ok_or_else(|| format!("expected \"<char> = <cell>\": {}", line))?;
+                    legend.insert(key, VaultCell::parse(description)?);
+                }
+                Section::Map => {
+                    let row = line.trim_end();
+                    if !row.is_empty() {
+                        rows.push(row.chars().collect::<Vec<_>>());
+                    }
+                }
+            }
+        }
+        let height = rows.len() as u32;
+        let width = rows.first().map(|row| row.len()).unwrap_or(0) as u32;
+        if width < 3 || height < 3 {
+            return Err("map must be at least 3x3".to_string());
+        }
+        if rows.iter().any(|row| row.len() as u32 != width) {
+            return Err("all rows of the map must be the same length".to_string());
+        }
+        let mut cells = Vec::new();
+        for ch in rows.into_iter().flatten() {
+            let cell = legend
+                .get(&ch)
+                .ok_or_else(|| format!("character not in legend: {:?}", ch))?;
+            cells.push(cell.clone());
+        }
+        let cells = Grid::new_fn(Size::new(width, height), |coord| {
+            cells[(coord.y as u32 * width + coord.x as u32) as usize].clone()
+        });
+        let vault = Self { cells };
+        vault.validate()?;
+        Ok(vault)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let size = self.cells.size();
+        for (coord, cell) in self.cells.edge_enumerate() {
+            match cell.level_cell {
+                LevelCell::Wall | LevelCell::CaveWall => (),
+                LevelCell::Door => {
+                    if Self::outward_direction(size, coord).is_none() {
+                        return Err(format!("door in corner at {:?}", coord));
+                    }
+                }
+                _ => {
+                    return Err(format!(
+                        "edge of vault must be walls or doors at {:?}",
+                        coord
+                    ))
+                }
+            }
+        }
+        if self.doors().next().is_none() {
+            return Err("vault must have at least one door in its edge".to_string());
+        }
+        for (coord, direction) in self.doors() {
+            let inside = coord - direction.coord();
+            if self.cells.get_checked(inside).level_cell.is_wall() {
+                return Err(format!("door at {:?} leads into a wall", coord));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn size(&self) -> Size {
+        self.cells.size()
+    }
+
+    // Returns the direction leading out of the vault from a coordinate on its edge, or `None` if
+    // the coordinate is a corner or not on the edge at all
+    fn outward_direction(size: Size, coord: Coord) -> Option<CardinalDirection> {
+        let on_left = coord.x == 0;
+        let on_right = coord.x == size.width() as i32 - 1;
+        let on_top = coord.y == 0;
+        let on_bottom = coord.y == size.height() as i32 - 1;
+        match (on_left, on_right, on_top, on_bottom) {
+            (true, false, false, false) => Some(CardinalDirection::West),
+            (false, true, false, false) => Some(CardinalDirection::East),
+            (false, false, true, false) => Some(CardinalDirection::North),
+            (false, false, false, true) => Some(CardinalDirection::South),
+            _ => None,
+        }
+    }
+
+    // Returns an iterator over the doors in the edge of the vault, along with the direction
+    // leading out of the vault from each door
+    pub fn doors(&self) -> impl '_ + Iterator<Item = (Coord, CardinalDirection)> {
+        let size = self.size();
+        self.cells
+            .edge_enumerate()
+            .filter_map(move |(coord, cell)| {
+                if cell.level_cell == LevelCell::Door {
+                    Self::outward_direction(size, coord).map(|direction| (coord, direction))
+                } else {
+                    None
+                }
+            })
+    }
+
+    // Returns a copy of the vault rotated clockwise by 90 degrees
+    fn rotate(&self) -> Self {
+        let size = self.size();
+        let rotated_size = Size::new(size.height(), size.width());
+        let cells = Grid::new_fn(rotated_size, |Coord { x, y }| {
+            self.cells
+                .get_checked(Coord::new(y, size.height() as i32 - 1 - x))
+                .clone()
+        });
+        Self { cells }
+    }
+
+    // Returns a copy of the vault flipped horizontally
+    fn mirror(&self) -> Self {
+        let size = self.size();
+        let cells = Grid::new_fn(size, |Coord { x, y }| {
+            self.cells
+                .get_checked(Coord::new(size.width() as i32 - 1 - x, y))
+                .clone()
+        });
+        Self { cells }
+    }
+
+    // Returns a copy of the vault with a random rotation and reflection applied
+    pub fn random_transform<R: Rng>(&self, rng: &mut R) -> Self {
+        let mut vault = if rng.gen() {
+            self.mirror()
+        } else {
+            self.clone()
+        };
+        for _ in 0..rng.gen_range(0..4) {
+            vault = vault.rotate();
+        }
+        vault
+    }
+}
+
+// Returns all the vaults bundled with the game
+pub fn bundled_vaults() -> Vec<Vault> {
+    BUNDLED_VAULTS
+        .iter()
+        .map(|(name, text)| {
+            Vault::parse(text).unwrap_or_else(|e| panic!("invalid vault {}: {}", name, e))
+        })
+        .collect()
+}